@@ -1,37 +1,177 @@
-use std::collections::LinkedList;
 use std::mem::size_of;
 
-use anchor_lang::prelude::Pubkey;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use nft_clob::account_states::{Book, MAX_ORDERS};
+use anchor_lang::prelude::{Clock, Pubkey};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use nft_clob::account_states::{Book, RingBufferCrank, RingBufferFilledExecReport};
+use nft_clob::enums::{CancelReason, OrderType};
 use nft_clob::instructions::NewOrderSingleIx;
+use rand::seq::SliceRandom;
 use rand::Rng;
 use slice_rbtree::tree::{tree_size, RBTree, TreeParams};
 
+const DEPTHS: [u64; 3] = [16, 256, 2000];
+const CAPACITY: u16 = 2048;
+const REPORT_CAPACITY: u16 = 200;
+const CRANK_CAPACITY: u16 = 4095;
+
+/// Zeroed account data of a book and its queues, aligned for zero-copy.
+struct Accounts {
+    book: Vec<u64>,
+    rb: Vec<u64>,
+    rb_crank: Vec<u64>,
+}
+
+impl Accounts {
+    fn new() -> Self {
+        let mut accounts = Self {
+            book: vec![0; Book::space(CAPACITY) / 8],
+            rb: vec![0; RingBufferFilledExecReport::space(REPORT_CAPACITY) / 8],
+            rb_crank: vec![0; RingBufferCrank::space(CRANK_CAPACITY) / 8],
+        };
+        Book::init(bytemuck::cast_slice_mut(&mut accounts.book)).unwrap();
+        RingBufferFilledExecReport::init(bytemuck::cast_slice_mut(&mut accounts.rb)).unwrap();
+        RingBufferCrank::init(bytemuck::cast_slice_mut(&mut accounts.rb_crank)).unwrap();
+        accounts
+    }
+
+    fn new_limit(&mut self, nos: &NewOrderSingleIx, maker: Pubkey) {
+        let mut book = Book::load_mut(bytemuck::cast_slice_mut(&mut self.book)).unwrap();
+        let mut rb =
+            RingBufferFilledExecReport::load_mut(bytemuck::cast_slice_mut(&mut self.rb)).unwrap();
+        let mut rb_crank =
+            RingBufferCrank::load_mut(bytemuck::cast_slice_mut(&mut self.rb_crank)).unwrap();
+        book.new_limit_from_nos_ix(
+            nos,
+            maker,
+            maker,
+            maker,
+            &mut rb,
+            &mut rb_crank,
+            None,
+            &Clock::default(),
+        )
+        .unwrap();
+    }
+}
+
+/// Bids at every other price from 2 up, one level per order, placed in
+/// random order.
+fn book_with_depth(depth: u64) -> Vec<u64> {
+    let mut accounts = Accounts::new();
+    let maker = Pubkey::new_unique();
+
+    let mut limits: Vec<u64> = (1..=depth).map(|i| i * 2).collect();
+    limits.shuffle(&mut rand::thread_rng());
+    for limit in limits {
+        let nos = NewOrderSingleIx::new(true, limit, 1);
+        accounts.new_limit(&nos, maker);
+    }
+    accounts.book
+}
+
+/// Copy of a book with empty queues, so every iteration starts equal.
+fn setup(book: &[u64]) -> Accounts {
+    let mut accounts = Accounts::new();
+    accounts.book.copy_from_slice(book);
+    accounts
+}
+
+/// Insert, cancel and match against books of increasing depth.
+fn depth_benchmark(c: &mut Criterion) {
+    let (maker, taker) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut group = c.benchmark_group("book depth");
+    for depth in DEPTHS {
+        let book = book_with_depth(depth);
+
+        // New level in the middle of the book.
+        group.bench_with_input(BenchmarkId::new("insert", depth), &book, |b, book| {
+            b.iter_batched(
+                || setup(book),
+                |mut accounts| {
+                    let nos = NewOrderSingleIx::new(true, depth + 1, 1);
+                    accounts.new_limit(&nos, maker);
+                    accounts
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        group.bench_with_input(BenchmarkId::new("cancel", depth), &book, |b, book| {
+            b.iter_batched(
+                || setup(book),
+                |mut accounts| {
+                    let mut book =
+                        Book::load_mut(bytemuck::cast_slice_mut(&mut accounts.book)).unwrap();
+                    let mut rb_crank =
+                        RingBufferCrank::load_mut(bytemuck::cast_slice_mut(&mut accounts.rb_crank))
+                            .unwrap();
+                    let pos = depth as u16 / 2;
+                    book.bids
+                        .cancel_order(
+                            pos,
+                            true,
+                            CancelReason::Maker,
+                            &mut rb_crank,
+                            None,
+                            &Clock::default(),
+                        )
+                        .unwrap();
+                    book.refresh_best_offers();
+                    accounts
+                },
+                BatchSize::LargeInput,
+            )
+        });
+
+        // Sweep of the best ten levels.
+        group.bench_with_input(BenchmarkId::new("match", depth), &book, |b, book| {
+            b.iter_batched(
+                || setup(book),
+                |mut accounts| {
+                    let mut nos = NewOrderSingleIx::new(false, 1, 10);
+                    nos.order_type = OrderType::IOC;
+                    accounts.new_limit(&nos, taker);
+                    accounts
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
 fn sort_arr_benchmark(c: &mut Criterion) {
     c.bench_function("insert 2000 orders", |b| {
         b.iter(|| {
-            let mut book = Book::new();
+            let mut accounts = Accounts::new();
             let maker = Pubkey::new_unique();
             let mut rng = rand::thread_rng();
-            for i in 1..512 {
+            for _ in 1..512 {
                 let nos = NewOrderSingleIx::new(true, rng.gen_range(1..150), 1);
-                book.new_limit(&nos, maker);
+                accounts.new_limit(&nos, maker);
             }
         })
     });
 
-    let mut book = Book::new();
+    let mut accounts = Accounts::new();
     let maker = Pubkey::new_unique();
     for i in 1..2000 {
         let nos = NewOrderSingleIx::new(true, 6000 - i, 1);
-        book.new_limit(&nos, maker);
+        accounts.new_limit(&nos, maker);
     }
 
+    // An order can only be removed once, so every iteration gets a fresh copy.
     c.bench_function("remove order", |b| {
-        b.iter(|| {
-            book.bids.remove_order(872);
-        })
+        b.iter_batched(
+            || setup(&accounts.book),
+            |mut accounts| {
+                let mut book =
+                    Book::load_mut(bytemuck::cast_slice_mut(&mut accounts.book)).unwrap();
+                book.bids.remove_order(872);
+                accounts
+            },
+            BatchSize::LargeInput,
+        )
     });
 
     c.bench_function("rbtree: insert 2000 orders", |b| {
@@ -41,14 +181,13 @@ fn sort_arr_benchmark(c: &mut Criterion) {
                     k_size: 8,
                     v_size: size_of::<NewOrderSingleIx>(),
                 },
-                MAX_ORDERS as usize,
+                CAPACITY as usize,
             );
 
             let mut buffer = vec![0; size];
-            const mem_size: usize = size_of::<NewOrderSingleIx>();
-            let mut rbtree: RBTree<u64, NewOrderSingleIx, 8, mem_size> =
+            const MEM_SIZE: usize = size_of::<NewOrderSingleIx>();
+            let mut rbtree: RBTree<u64, NewOrderSingleIx, 8, MEM_SIZE> =
                 RBTree::init_slice(&mut buffer).unwrap();
-            let maker = Pubkey::new_unique();
 
             let mut rng = rand::thread_rng();
             for i in 1..512 {
@@ -58,20 +197,18 @@ fn sort_arr_benchmark(c: &mut Criterion) {
         })
     });
 
-
     let size = tree_size(
         TreeParams {
             k_size: 8,
             v_size: size_of::<NewOrderSingleIx>(),
         },
-        MAX_ORDERS as usize,
+        CAPACITY as usize,
     );
 
     let mut buffer = vec![0; size];
     const MEM_SIZE: usize = size_of::<NewOrderSingleIx>();
     let mut rbtree: RBTree<u64, NewOrderSingleIx, 8, MEM_SIZE> =
         RBTree::init_slice(&mut buffer).unwrap();
-    let maker = Pubkey::new_unique();
 
     let mut rng = rand::thread_rng();
     for i in 1..512 {
@@ -79,7 +216,7 @@ fn sort_arr_benchmark(c: &mut Criterion) {
         rbtree.insert(i, nos).unwrap();
     }
 
-    c.bench_function("remove order", |b| {
+    c.bench_function("rbtree: remove order", |b| {
         b.iter(|| {
             let val = 247;
             rbtree.remove_entry(&val);
@@ -115,5 +252,5 @@ fn sort_arr_benchmark(c: &mut Criterion) {
     // });
 }
 
-criterion_group!(benches, sort_arr_benchmark);
+criterion_group!(benches, sort_arr_benchmark, depth_benchmark);
 criterion_main!(benches);
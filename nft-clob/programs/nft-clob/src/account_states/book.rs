@@ -1,37 +1,117 @@
+use std::{
+    mem::size_of,
+    ops::{Deref, DerefMut},
+};
+
 use anchor_lang::prelude::*;
 
-use crate::{enums::OrderType, instructions::new_order_single::NewOrderSingleIx};
+use crate::{
+    enums::{CancelReason, OrderType, OverflowPolicy, RejectReason, SelfTradeBehavior},
+    events::{emit_fill, OrderAccepted, OrderCancelled, OrderEvicted, OrderRejected},
+    instructions::new_order_single::NewOrderSingleIx,
+};
+
+use crate::errors::ErrorCode;
 
-use super::{Crank, Order, RingBufferCrank, RingBufferFilledExecReport, Side};
+use super::{OpenOrders, Order, RingBufferCrank, RingBufferFilledExecReport, Side};
 
-pub const MAX_ORDERS: u16 = 2048;
+pub const MAX_ORDERS: u16 = u16::MAX - 1; // Largest capacity of a side.
 
 /// Central Limit Order Book
+///
+/// The header is followed by the asks and the bids, each sized for the
+/// capacity of orders recorded in the header.
 #[account(zero_copy)]
-pub struct Book {
-    pub instrmt: Pubkey, // Instrument that the book belongs to
-    pub last_price: u64, // Limit of last executed trade
-    pub ask_min: u64,    // Best ask
-    pub bid_max: u64,    // Best bid
-    pub asks: Side,      // Ask side
-    pub bids: Side,      // Bid side
+pub struct BookHeader {
+    pub instrmt: Pubkey,         // Instrument that the book belongs to
+    pub last_price: u64,         // Limit of last executed trade
+    pub ask_min: u64,            // Best ask
+    pub bid_max: u64,            // Best bid
+    pub fees_accrued: u64,       // Quote fees not yet swept to the treasury
+    pub last_order_id: u64,      // Order id assigned to the last new order
+    pub tick_size: u64,          // Price increment of the instrument
+    pub min_eviction_ticks: u64, // Ticks a new order must beat the worst order by to evict it
+    pub capacity: u16,           // Max orders per side, sized from the account
+    pub overflow_policy: u8,     // OverflowPolicy applied when a side is full
+    padding: [u8; 5],
+}
+
+/// Book over the account data.
+pub struct Book<'a> {
+    header: &'a mut BookHeader,
+    pub asks: Side<'a>, // Ask side
+    pub bids: Side<'a>, // Bid side
 }
 
 #[cfg(test)]
-impl Book {
-    pub fn new() -> Self {
-        Book {
-            instrmt: Pubkey::default(),
-            last_price: 0,
-            ask_min: 0,
-            bid_max: 0,
-            asks: Side::new(),
-            bids: Side::new(),
-        }
+impl<'a> Book<'a> {
+    /// Book with a capacity of orders per side over a zeroed buffer.
+    pub fn new(data: &'a mut Vec<u64>, capacity: u16) -> Self {
+        data.resize(Self::space(capacity) / 8, 0);
+        let data: &mut [u8] = bytemuck::cast_slice_mut(data);
+        Self::init(data).unwrap()
+    }
+}
+
+impl<'a> Book<'a> {
+    /// Sizes a new book from the length of the account data.
+    pub fn init(data: &'a mut [u8]) -> Result<Self> {
+        let capacity = ((data.len().saturating_sub(Self::space(0)) / 2)
+            / (Side::space(1) - Side::space(0)))
+        .min(MAX_ORDERS as usize) as u16;
+        require!(capacity > 0, ErrorCode::InvalidCapacity);
+        let header: &mut BookHeader =
+            bytemuck::try_from_bytes_mut(&mut data[8..8 + size_of::<BookHeader>()])
+                .map_err(|_| ErrorCode::InvalidCapacity)?;
+        header.capacity = capacity;
+        Self::load_mut(data)
+    }
+
+    /// Book over the account data, including the discriminator.
+    pub fn load_mut(data: &'a mut [u8]) -> Result<Self> {
+        require!(data.len() >= Self::space(0), ErrorCode::InvalidCapacity);
+        let (header, sides) = data[8..].split_at_mut(size_of::<BookHeader>());
+        let header: &mut BookHeader =
+            bytemuck::try_from_bytes_mut(header).map_err(|_| ErrorCode::InvalidCapacity)?;
+        let (capacity, instrmt) = (header.capacity, header.instrmt);
+        require!(
+            capacity > 0 && capacity <= MAX_ORDERS && sides.len() >= 2 * Side::space(capacity),
+            ErrorCode::InvalidCapacity
+        );
+        let (asks, bids) = sides.split_at_mut(Side::space(capacity));
+        Ok(Self {
+            header,
+            asks: Side::from_bytes(asks, capacity, instrmt)?,
+            bids: Side::from_bytes(bids, capacity, instrmt)?,
+        })
+    }
+
+    pub fn space(capacity: u16) -> usize {
+        8 + size_of::<BookHeader>() + 2 * Side::space(capacity)
+    }
+}
+
+impl Deref for Book<'_> {
+    type Target = BookHeader;
+
+    fn deref(&self) -> &Self::Target {
+        self.header
+    }
+}
+
+impl DerefMut for Book<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.header
     }
 }
 
-impl Book {
+impl Book<'_> {
+    pub fn set_instrmt(&mut self, instrmt: Pubkey) {
+        self.header.instrmt = instrmt;
+        self.asks.instrmt = instrmt;
+        self.bids.instrmt = instrmt;
+    }
+
     /// When buying you deposit quote to create a bid
     pub fn get_quote_vault(&self) -> Pubkey {
         self.bids.vault
@@ -47,6 +127,13 @@ impl Book {
         self.asks.vault = vault;
     }
 
+    /// Best bid and ask after orders have been removed outside of matching.
+    pub fn refresh_best_offers(&mut self) {
+        self.header.ask_min = self.asks.best_offer();
+        self.header.bid_max = self.bids.best_offer();
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn new_limit_from_nos_ix(
         &mut self,
         nos: &NewOrderSingleIx,
@@ -55,7 +142,9 @@ impl Book {
         payment_acc: Pubkey,
         rb_filled_exec_report: &mut RingBufferFilledExecReport,
         rb_crank: &mut RingBufferCrank,
-    ) -> Order {
+        open_orders: Option<&mut OpenOrders>,
+        clock: &Clock,
+    ) -> Result<Order> {
         let new_order = nos.into_order(maker, payout_acc, payment_acc);
         self.new_limit(
             new_order,
@@ -63,10 +152,91 @@ impl Book {
             nos.is_buy,
             rb_filled_exec_report,
             rb_crank,
+            open_orders,
+            clock,
         )
     }
 
+    /// Price gap by which a new order must beat the worst order of a full
+    /// side to evict it, None if full sides reject new orders.
+    pub fn get_min_eviction_gap(&self) -> Option<u64> {
+        match OverflowPolicy::from_u8(self.header.overflow_policy) {
+            OverflowPolicy::Reject => None,
+            OverflowPolicy::Evict => {
+                Some(self.header.min_eviction_ticks.max(1) * self.header.tick_size.max(1))
+            }
+        }
+    }
+
+    /// Dry run of matching, the quantity an incoming order could fill now.
+    pub fn fillable_qty(&self, new_order: &Order, is_buy: bool, now: i64) -> u64 {
+        match is_buy {
+            true => self.asks.fillable_qty(new_order, is_buy, now),
+            false => self.bids.fillable_qty(new_order, is_buy, now),
+        }
+    }
+
+    /// Rejects a maker-only order that would cross the opposite best offer,
+    /// or slides it one tick behind it. Runs before the book is mutated.
+    fn apply_maker_only(
+        &self,
+        new_order: &mut Order,
+        order_type: OrderType,
+        is_buy: bool,
+        slot: u64,
+    ) -> Result<()> {
+        let crosses = match is_buy {
+            true => !self.asks.is_empty() && new_order.limit >= self.header.ask_min,
+            false => !self.bids.is_empty() && new_order.limit <= self.header.bid_max,
+        };
+        if !crosses {
+            return Ok(());
+        }
+        let reject = || {
+            Self::reject(
+                self.header.instrmt,
+                new_order,
+                is_buy,
+                RejectReason::MakerOnlyCrossed,
+                ErrorCode::MakerOnlyFailed,
+                slot,
+            )
+        };
+        if order_type != OrderType::MOS {
+            return Err(reject());
+        }
+
+        let tick_size = self.header.tick_size.max(1);
+        let limit = match is_buy {
+            true => self.header.ask_min.saturating_sub(tick_size),
+            false => self.header.bid_max + tick_size,
+        };
+        if limit == 0 {
+            return Err(reject());
+        }
+        new_order.limit = limit;
+        Ok(())
+    }
+
+    /// Emits the rejection of a new order and returns the error failing the
+    /// transaction.
+    fn reject(
+        instrmt: Pubkey,
+        order: &Order,
+        is_buy: bool,
+        reason: RejectReason,
+        error: ErrorCode,
+        slot: u64,
+    ) -> Error {
+        emit!(OrderRejected::new(instrmt, order, is_buy, reason, slot));
+        error.into()
+    }
+
     /// Process an incoming new order single.
+    ///
+    /// Makers are paid through the crank, into their open orders if they placed
+    /// the order with them. Refunds to the open orders at hand are direct.
+    #[allow(clippy::too_many_arguments)]
     pub fn new_limit(
         &mut self,
         mut new_order: Order,
@@ -74,7 +244,40 @@ impl Book {
         is_buy: bool,
         rb_filled_exec_report: &mut RingBufferFilledExecReport,
         rb_crank: &mut RingBufferCrank,
-    ) -> Order {
+        mut open_orders: Option<&mut OpenOrders>,
+        clock: &Clock,
+    ) -> Result<Order> {
+        let slot = clock.slot;
+        if order_type == OrderType::MO || order_type == OrderType::MOS {
+            self.apply_maker_only(&mut new_order, order_type, is_buy, slot)?;
+        }
+
+        // Fill or kill before any fill is written.
+        if order_type == OrderType::FOK
+            && self.fillable_qty(&new_order, is_buy, clock.unix_timestamp)
+                != new_order.get_leaves_qty()
+        {
+            return Err(Self::reject(
+                self.header.instrmt,
+                &new_order,
+                is_buy,
+                RejectReason::FillOrKillUnfilled,
+                ErrorCode::FillOrKillFailed,
+                slot,
+            ));
+        }
+
+        self.header.last_order_id += 1;
+        new_order.set_order_id(self.header.last_order_id);
+        new_order.set_order_type(order_type);
+        emit!(OrderAccepted::new(
+            self.header.instrmt,
+            &new_order,
+            is_buy,
+            order_type,
+            slot
+        ));
+
         let is_match = match is_buy {
             true => |order_price: u64, nos_limit: u64| -> bool { order_price <= nos_limit },
             false => |order_price: u64, nos_limit: u64| -> bool { order_price >= nos_limit },
@@ -86,77 +289,254 @@ impl Book {
         };
 
         let (match_side, has_matches) = match is_buy {
-            true => (&mut self.asks, new_order.limit >= self.ask_min),
-            false => (&mut self.bids, new_order.limit <= self.bid_max),
+            true => (&mut self.asks, new_order.limit >= self.header.ask_min),
+            false => (&mut self.bids, new_order.limit <= self.header.bid_max),
         };
 
-        if !match_side.is_empty() {
-            if has_matches {
-                let mut pos = match_side.head;
-                loop {
-                    if !is_match(match_side.orders[pos as usize].order.limit, new_order.limit) {
-                        break; // new order outside price range
-                    }
-                    let filled_exec_report = match_side.orders[pos as usize]
-                        .order
-                        .execute_trade(&mut new_order, is_buy)
-                        .unwrap();
-                    rb_filled_exec_report.insert(filled_exec_report);
-
-                    // Update the books' last price
-                    self.last_price = filled_exec_report.price;
-
-                    rb_crank.insert(
-                        crank_vault,
-                        match_side.orders[pos as usize].order.payout_acc,
-                        is_buy,
-                        match_side.orders[pos as usize].order.maker,
-                        filled_exec_report.quantity,
-                        filled_exec_report.price,
-                    );
+        if !match_side.is_empty() && has_matches {
+            let now = clock.unix_timestamp;
+            let mut pos = match_side.head;
+            loop {
+                if !is_match(match_side.orders[pos as usize].order.limit, new_order.limit) {
+                    break; // new order outside price range
+                }
 
+                if match_side.orders[pos as usize].order.is_expired(now) {
                     let next_pos = match_side.next_order(pos);
+                    match_side.cancel_order(
+                        pos,
+                        !is_buy,
+                        CancelReason::Expired,
+                        rb_crank,
+                        open_orders.as_deref_mut(),
+                        clock,
+                    )?;
+                    match is_buy {
+                        true => self.header.ask_min = match_side.best_offer(),
+                        false => self.header.bid_max = match_side.best_offer(),
+                    };
+                    pos = match next_pos {
+                        None => break,
+                        Some(next_pos) => next_pos,
+                    };
+                    continue;
+                }
 
-                    if match_side.orders[pos as usize].order.is_filled() {
-                        match_side.remove_order(pos);
-                        match is_buy {
-                            true => self.ask_min = match_side.best_offer(),
-                            false => self.bid_max = match_side.best_offer(),
-                        };
-                    }
+                if new_order.get_affordable_qty(match_side.orders[pos as usize].order.limit) == 0 {
+                    break; // quote budget spent
+                }
 
+                if match_side.orders[pos as usize].order.maker == new_order.maker {
+                    let next_pos = match_side.next_order(pos);
+                    match_side.prevent_self_trade(
+                        pos,
+                        &mut new_order,
+                        !is_buy,
+                        rb_crank,
+                        open_orders.as_deref_mut(),
+                        clock,
+                    )?;
+                    match is_buy {
+                        true => self.header.ask_min = match_side.best_offer(),
+                        false => self.header.bid_max = match_side.best_offer(),
+                    };
                     if new_order.is_filled() {
-                        return new_order; // new order is filled
+                        // nothing left after decrement
+                        emit!(OrderCancelled::new(
+                            self.header.instrmt,
+                            &new_order,
+                            is_buy,
+                            CancelReason::SelfTrade,
+                            slot
+                        ));
+                        return Ok(new_order);
                     }
-
                     pos = match next_pos {
                         None => break,
                         Some(next_pos) => next_pos,
                     };
+                    continue;
+                }
+
+                let filled_exec_report = match_side.orders[pos as usize].order.execute_trade(
+                    &mut new_order,
+                    is_buy,
+                    clock,
+                )?;
+                rb_filled_exec_report.insert(filled_exec_report);
+                match_side.reduce_level_qty(pos, filled_exec_report.quantity);
+                emit_fill(
+                    self.header.instrmt,
+                    &match_side.orders[pos as usize].order,
+                    !is_buy,
+                    filled_exec_report.price,
+                    filled_exec_report.quantity,
+                    slot,
+                );
+                emit_fill(
+                    self.header.instrmt,
+                    &new_order,
+                    is_buy,
+                    filled_exec_report.price,
+                    filled_exec_report.quantity,
+                    slot,
+                );
+
+                // Update the books' last price
+                self.header.last_price = filled_exec_report.price;
+
+                self.header.fees_accrued = filled_exec_report
+                    .maker_fee
+                    .checked_add(filled_exec_report.taker_fee)
+                    .and_then(|fees| self.header.fees_accrued.checked_add(fees))
+                    .ok_or(ErrorCode::FillOverflow)?;
+
+                // Sellers are paid in quote minus the maker fee, buyers in base.
+                let match_cost = filled_exec_report
+                    .quantity
+                    .checked_mul(filled_exec_report.price)
+                    .ok_or(ErrorCode::FillOverflow)?;
+                let (payout, deposit_consumed) = match is_buy {
+                    true => (
+                        match_cost.checked_sub(filled_exec_report.maker_fee),
+                        Some(filled_exec_report.quantity),
+                    ),
+                    false => (
+                        Some(filled_exec_report.quantity),
+                        match_cost.checked_add(filled_exec_report.maker_fee),
+                    ),
+                };
+                let payout = payout.ok_or(ErrorCode::FillOverflow)?;
+                let deposit_consumed = deposit_consumed.ok_or(ErrorCode::FillOverflow)?;
+                let maker_order = &match_side.orders[pos as usize].order;
+                // Open orders release the deposit the fill consumed.
+                let released = match maker_order.uses_open_orders() {
+                    true => deposit_consumed,
+                    false => 0,
+                };
+                rb_crank.insert(
+                    crank_vault,
+                    maker_order.payout_acc,
+                    maker_order.maker,
+                    payout,
+                    released,
+                )?;
+
+                let next_pos = match_side.next_order(pos);
+
+                if match_side.orders[pos as usize].order.is_filled() {
+                    match_side.remove_order(pos);
+                    match is_buy {
+                        true => self.header.ask_min = match_side.best_offer(),
+                        false => self.header.bid_max = match_side.best_offer(),
+                    };
                 }
+
+                if new_order.is_filled() {
+                    return Ok(new_order); // new order is filled
+                }
+
+                pos = match next_pos {
+                    None => break,
+                    Some(next_pos) => next_pos,
+                };
             }
         }
 
-        if order_type == OrderType::IOC {
+        // Market orders never rest on the book.
+        if order_type == OrderType::IOC || order_type == OrderType::MKT {
+            emit!(OrderCancelled::new(
+                self.header.instrmt,
+                &new_order,
+                is_buy,
+                CancelReason::Unfilled,
+                slot
+            ));
             new_order.clear_leaves_qty();
-            return new_order;
+            return Ok(new_order);
         }
 
-        match is_buy {
+        let min_eviction_gap = self.get_min_eviction_gap();
+        let instrmt = self.header.instrmt;
+        let reject_book_full = |err: Error| match err == ErrorCode::BookFull.into() {
+            true => Self::reject(
+                instrmt,
+                &new_order,
+                is_buy,
+                RejectReason::BookFull,
+                ErrorCode::BookFull,
+                slot,
+            ),
+            false => err,
+        };
+        let evicted_order = match is_buy {
             true => {
-                self.bids.insert_order(new_order, is_buy, rb_crank);
-                if new_order.limit > self.bid_max {
-                    self.bid_max = new_order.limit;
+                let evicted_order = self
+                    .bids
+                    .insert_order(
+                        new_order,
+                        is_buy,
+                        min_eviction_gap,
+                        rb_crank,
+                        open_orders,
+                        clock,
+                    )
+                    .map_err(reject_book_full)?;
+                if new_order.limit > self.header.bid_max {
+                    self.header.bid_max = new_order.limit;
                 }
+                evicted_order
             }
             false => {
-                self.asks.insert_order(new_order, is_buy, rb_crank);
-                if self.ask_min == 0 || new_order.limit < self.ask_min {
-                    self.ask_min = new_order.limit;
+                let evicted_order = self
+                    .asks
+                    .insert_order(
+                        new_order,
+                        is_buy,
+                        min_eviction_gap,
+                        rb_crank,
+                        open_orders,
+                        clock,
+                    )
+                    .map_err(reject_book_full)?;
+                if self.header.ask_min == 0 || new_order.limit < self.header.ask_min {
+                    self.header.ask_min = new_order.limit;
                 }
+                evicted_order
             }
+        };
+        if let Some(evicted_order) = evicted_order {
+            self.refresh_best_offers();
+            emit!(OrderEvicted::new(
+                self.header.instrmt,
+                &evicted_order,
+                is_buy,
+                new_order.get_order_id(),
+                slot
+            ));
         }
-        return new_order;
+        Ok(new_order)
+    }
+}
+
+impl Book<'_> {
+    /// Cancels expired orders of both sides, visiting at most max_orders.
+    /// Returns the number of orders pruned.
+    pub fn prune_expired(
+        &mut self,
+        max_orders: u16,
+        rb_crank: &mut RingBufferCrank,
+        clock: &Clock,
+    ) -> Result<u16> {
+        let mut budget = max_orders;
+        let pruned = self
+            .asks
+            .prune_expired(false, &mut budget, rb_crank, clock)?
+            + self
+                .bids
+                .prune_expired(true, &mut budget, rb_crank, clock)?;
+        self.refresh_best_offers();
+        Ok(pruned)
     }
 }
 
@@ -167,37 +547,93 @@ impl NewOrderSingleIx {
             limit,
             size,
             order_type: crate::enums::OrderType::GTC,
+            client_order_id: None,
+            self_trade_behavior: SelfTradeBehavior::DecrementAndCancel,
+            quote_budget: None,
+            expiry: None,
         }
     }
+    /// Market orders match any price, buys at most the quote budget if set.
     pub fn into_order(&self, maker: Pubkey, payout_acc: Pubkey, payment_acc: Pubkey) -> Order {
-        Order::new(self.limit, self.size, maker, payout_acc, payment_acc)
+        let limit = match (self.order_type, self.is_buy) {
+            (OrderType::MKT, true) => u64::MAX,
+            (OrderType::MKT, false) => 0,
+            _ => self.limit,
+        };
+        let mut order = Order::new(limit, self.size, maker, payout_acc, payment_acc);
+        order.set_quote_budget(self.quote_budget);
+        order.set_expiry(self.expiry);
+        order.set_client_order_id(self.client_order_id.unwrap_or(0));
+        order.set_self_trade_behavior(self.self_trade_behavior);
+        order
     }
 }
 
 #[cfg(test)]
 mod test {
+    use std::{cell::RefCell, collections::HashMap, sync::Once};
 
     use crate::{
-        account_states::{Order, RingBufferCrank, RingBufferFilledExecReport},
-        enums::OrderType,
+        account_states::{OpenOrders, Order, RingBufferCrank, RingBufferFilledExecReport},
+        enums::{
+            BookSide, CancelReason, OrderType, OverflowPolicy, RejectReason, SelfTradeBehavior,
+        },
+        errors::ErrorCode,
+        events::{
+            OrderAccepted, OrderCancelled, OrderEvicted, OrderFilled, OrderPartiallyFilled,
+            OrderRejected,
+        },
     };
 
-    use super::{Book, MAX_ORDERS};
+    use super::{Book, Side};
+    use anchor_lang::prelude::{Clock, Pubkey};
+    use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+    use anchor_lang::{AnchorDeserialize, Discriminator};
     use quickcheck::TestResult;
     use quickcheck_macros::quickcheck;
     const GTC: OrderType = OrderType::GTC;
+    const CAPACITY: u16 = 2048;
+    const REPORT_CAPACITY: u16 = 200;
+    const CRANK_CAPACITY: u16 = 4095;
+    const CLOCK: Clock = Clock {
+        slot: 1,
+        epoch_start_timestamp: 0,
+        epoch: 0,
+        leader_schedule_epoch: 0,
+        unix_timestamp: 2,
+    };
+
+    /// Account data of a book and its queues, borrowed by the views.
+    #[derive(Default)]
+    struct Accounts {
+        book: Vec<u64>,
+        rb: Vec<u64>,
+        rb_crank: Vec<u64>,
+    }
+
+    impl Accounts {
+        /// Empty book with a capacity of orders per side and its queues.
+        fn load(&mut self, capacity: u16) -> (Book, RingBufferFilledExecReport, RingBufferCrank) {
+            (
+                Book::new(&mut self.book, capacity),
+                RingBufferFilledExecReport::new(&mut self.rb, REPORT_CAPACITY),
+                RingBufferCrank::new(&mut self.rb_crank, CRANK_CAPACITY),
+            )
+        }
+    }
 
     #[test]
     fn it_should_add_single_order_to_both_sides() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
 
         let sell_nos = Order::new_test(10, 3);
         let buy_nos = Order::new_test(9, 2);
 
-        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank);
-        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
 
         assert_eq!(book.ask_min, 10);
         assert_eq!(book.asks.orders[0].order.limit, 10);
@@ -212,16 +648,16 @@ mod test {
 
     #[quickcheck]
     fn it_should_add_many_orders_to_asks_side(mut sell_limits: Vec<u64>) -> bool {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
         let size = 2;
         for i in sell_limits.iter() {
             if *i == 0 {
                 continue;
             }
-            let sell_nos = Order::new_test(i.clone(), size);
-            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank);
+            let sell_nos = Order::new_test(*i, size);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
         }
 
         sell_limits.sort();
@@ -239,15 +675,15 @@ mod test {
 
     #[test]
     fn it_should_add_many_orders_to_bids_side() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
         let size = 2;
 
         let mut buy_limits = [10, 11, 12, 13, 14, 15, 16, 9, 25, 12, 8, 7, 8, 6, 6, 19];
         for i in buy_limits {
             let buy_nos = Order::new_test(i, size);
-            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank);
+            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
         }
 
         buy_limits.sort_by(|a, b| b.cmp(a));
@@ -260,35 +696,38 @@ mod test {
     }
     #[test]
     fn it_should_add_many_orders_with_incremental_price() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
         let size = 2;
 
-        for i in 1..MAX_ORDERS {
+        for i in 1..CAPACITY {
             let buy_nos = Order::new_test(i as u64, size);
-            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank);
+            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
         }
 
-        for i in 1..MAX_ORDERS {
+        for i in 1..CAPACITY {
             let sell_nos = Order::new_test(i as u64, size);
-            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
         }
     }
 
     #[test]
     fn it_should_match_a_few_orders() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
 
-        let buy_nos_1 = Order::new_test(11 as u64, 2);
-        book.new_limit(buy_nos_1, GTC, true, &mut rb, &mut rb_crank);
+        let buy_nos_1 = Order::new_test(11, 2);
+        book.new_limit(buy_nos_1, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
         let buy_nos_2 = Order::new_test(10, 4);
-        book.new_limit(buy_nos_2, GTC, true, &mut rb, &mut rb_crank);
+        book.new_limit(buy_nos_2, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
 
         let sell_nos_1 = Order::new_test(10, 1);
-        book.new_limit(sell_nos_1, GTC, false, &mut rb, &mut rb_crank);
+        book.new_limit(sell_nos_1, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
 
         assert_eq!(book.asks.orders[0].order.get_leaves_qty(), 0);
         assert_eq!(book.asks.orders[0].order.get_cum_qty(), 0);
@@ -302,7 +741,8 @@ mod test {
         assert_eq!(book.bids.orders[1].order.limit, 10);
 
         let sell_nos_2 = Order::new_test(10, 2);
-        book.new_limit(sell_nos_2, GTC, false, &mut rb, &mut rb_crank);
+        book.new_limit(sell_nos_2, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
 
         assert_eq!(book.bids.orders[0].order.get_leaves_qty(), 0);
         assert_eq!(book.bids.orders[0].order.get_cum_qty(), 0);
@@ -315,19 +755,22 @@ mod test {
 
     #[test]
     fn it_should_place_a_few_orders_1() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
 
         let buy_nos_1 = Order::new_test(182, 123);
         let buy_nos_2 = Order::new_test(255, 184);
         let sell_nos_1 = Order::new_test(23, 33);
         let sell_nos_2 = Order::new_test(189, 31);
 
-        book.new_limit(buy_nos_1, GTC, true, &mut rb, &mut rb_crank);
-        book.new_limit(sell_nos_1, GTC, false, &mut rb, &mut rb_crank);
-        book.new_limit(buy_nos_2, GTC, true, &mut rb, &mut rb_crank);
-        book.new_limit(sell_nos_2, GTC, false, &mut rb, &mut rb_crank);
+        book.new_limit(buy_nos_1, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        book.new_limit(sell_nos_1, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        book.new_limit(buy_nos_2, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        book.new_limit(sell_nos_2, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
 
         assert_eq!(book.bids.tail, 0);
         assert_eq!(book.bids.head, 1);
@@ -339,23 +782,23 @@ mod test {
 
     #[test]
     fn it_should_place_a_few_orders_2() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
         let mut buy_orders = [(12, 216), (179, 98)].to_vec();
         let mut sell_orders = [(22, 100), (51, 147)].to_vec();
 
         loop {
             let (price, size) = buy_orders.remove(0);
-            println!("price {}", price);
             let buy_nos = Order::new_test(price, size);
-            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank);
+            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
 
             let (price, size) = sell_orders.remove(0);
             let sell_nos = Order::new_test(price, size);
-            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
 
-            if buy_orders.len() == 0 && sell_orders.len() == 0 {
+            if buy_orders.is_empty() && sell_orders.is_empty() {
                 break;
             }
         }
@@ -375,23 +818,23 @@ mod test {
 
     #[test]
     fn it_should_place_a_few_orders_3() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
         let mut buy_orders = [(255, 95), (197, 236)].to_vec();
         let mut sell_orders = [(199, 196), (91, 3)].to_vec();
 
         loop {
             let (price, size) = buy_orders.remove(0);
-            println!("price {}", price);
             let buy_nos = Order::new_test(price, size);
-            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank);
+            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
 
             let (price, size) = sell_orders.remove(0);
             let sell_nos = Order::new_test(price, size);
-            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
 
-            if buy_orders.len() == 0 && sell_orders.len() == 0 {
+            if buy_orders.is_empty() && sell_orders.is_empty() {
                 break;
             }
         }
@@ -409,23 +852,23 @@ mod test {
 
     #[test]
     fn it_should_place_a_few_orders_4() {
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
         let mut buy_orders = [(226, 135), (183, 46)].to_vec();
         let mut sell_orders = [(38, 157), (1, 148)].to_vec();
 
         loop {
             let (price, size) = buy_orders.remove(0);
-            println!("price {}", price);
             let buy_nos = Order::new_test(price, size);
-            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank);
+            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
 
             let (price, size) = sell_orders.remove(0);
             let sell_nos = Order::new_test(price, size);
-            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
 
-            if buy_orders.len() == 0 && sell_orders.len() == 0 {
+            if buy_orders.is_empty() && sell_orders.is_empty() {
                 break;
             }
         }
@@ -439,11 +882,1069 @@ mod test {
         assert_eq!(book.asks.head, 0);
         assert_eq!(book.asks.orders[0].order.limit, 1);
         assert_eq!(book.asks.orders[0].order.get_leaves_qty(), 124);
+    }
+
+    #[test]
+    fn it_should_assign_order_ids_and_echo_them_in_fills() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        let mut sell_nos = Order::new_test(10, 2);
+        sell_nos.set_client_order_id(42);
+        let sell_order = book
+            .new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        let buy_nos = Order::new_test(10, 1);
+        let buy_order = book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        assert_eq!(sell_order.get_order_id(), 1);
+        assert_eq!(buy_order.get_order_id(), 2);
+        assert_eq!(
+            book.asks.orders[book.asks.head as usize]
+                .order
+                .get_order_id(),
+            1
+        );
+
+        let report = rb.filled_exec_reports[0];
+        assert_eq!(report.maker_order_id, 1);
+        assert_eq!(report.maker_client_order_id, 42);
+        assert_eq!(report.taker_order_id, 2);
+        assert_eq!(report.taker_client_order_id, 0);
+    }
+
+    #[test]
+    fn it_should_cancel_orders_of_maker_with_cursor() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+        let maker_a = Pubkey::new_unique();
+        let maker_b = Pubkey::new_unique();
+
+        for i in 0..6 {
+            let mut sell_nos = Order::new_test(10 + i, 1);
+            sell_nos.maker = if i % 2 == 0 { maker_a } else { maker_b };
+            sell_nos.set_client_order_id(100 + i);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
 
-        println!(
-            "{:?},{:?},{:?}",
-            book.bids.orders[0], book.bids.orders[1], book.bids.orders[2]
+        assert_eq!(book.asks.find_by_client_order_id(&maker_a, 101), None);
+        let pos = book.asks.find_by_client_order_id(&maker_b, 101).unwrap();
+        assert_eq!(book.asks.orders[pos as usize].order.limit, 11);
+
+        // Budget runs out after cancelling the best ask of maker a.
+        let mut budget = 2;
+        let head = book.asks.head;
+        let cursor = book
+            .asks
+            .cancel_orders(
+                &maker_a,
+                false,
+                0..=u64::MAX,
+                head,
+                &mut budget,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        book.refresh_best_offers();
+        assert_eq!(budget, 0);
+        assert_eq!(book.ask_min, 11);
+        let cursor = cursor.unwrap();
+        assert_eq!(book.asks.orders[cursor as usize].order.limit, 12);
+
+        let mut budget = 10;
+        let cursor = book.asks.cancel_orders(
+            &maker_a,
+            false,
+            0..=u64::MAX,
+            cursor,
+            &mut budget,
+            &mut rb_crank,
+            None,
+            &CLOCK,
         );
+        assert_eq!(cursor.unwrap(), None);
+        assert_eq!(budget, 6);
+
+        // Only orders within the price range are cancelled.
+        let mut budget = 10;
+        let head = book.asks.head;
+        book.asks
+            .cancel_orders(
+                &maker_b,
+                false,
+                12..=14,
+                head,
+                &mut budget,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+
+        let mut limits = vec![];
+        let mut pos = book.asks.head;
+        loop {
+            let order = book.asks.orders[pos as usize].order;
+            assert_eq!(order.maker, maker_b);
+            limits.push(order.limit);
+            pos = match book.asks.next_order(pos) {
+                None => break,
+                Some(next_pos) => next_pos,
+            };
+        }
+        assert_eq!(limits, vec![11, 15]);
+
+        // Refunds for every cancelled order.
+        assert_eq!(rb_crank.cranks[0].get_quantity(), 1);
+        assert_eq!(rb_crank.cranks[3].get_quantity(), 1);
+        assert_eq!(rb_crank.cranks[4].get_quantity(), 0);
+    }
+
+    #[test]
+    fn it_should_amend_orders() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        let first = book
+            .new_limit(
+                Order::new_test(10, 5),
+                GTC,
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        book.new_limit(
+            Order::new_test(10, 5),
+            GTC,
+            true,
+            &mut rb,
+            &mut rb_crank,
+            None,
+            &CLOCK,
+        )
+        .unwrap();
+        let head = book.bids.head;
+        assert_eq!(
+            book.bids.orders[head as usize].order.get_order_id(),
+            first.get_order_id()
+        );
+
+        // Size reduction keeps priority and refunds the difference.
+        book.bids
+            .reduce_order(head, 2, true, &mut rb_crank, None)
+            .unwrap();
+        assert_eq!(book.bids.head, head);
+        assert_eq!(book.bids.orders[head as usize].order.get_leaves_qty(), 2);
+        assert_eq!(rb_crank.cranks[0].get_quantity(), 30);
+
+        // Price change re-inserts and matches immediately.
+        book.new_limit(
+            Order::new_test(12, 3),
+            GTC,
+            false,
+            &mut rb,
+            &mut rb_crank,
+            None,
+            &CLOCK,
+        )
+        .unwrap();
+        let removed_order = book.bids.remove_order(head);
+        book.refresh_best_offers();
+        let amended = book
+            .new_limit(
+                removed_order.amend(12, 2, CLOCK.unix_timestamp).unwrap(),
+                GTC,
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+
+        assert!(amended.is_filled());
+        assert_eq!(amended.get_cum_cost(), 24);
+        assert!(amended.get_order_id() > first.get_order_id());
+        assert_eq!(book.bid_max, 10);
+        assert_eq!(book.ask_min, 12);
+    }
+
+    #[test]
+    fn it_should_credit_open_orders_of_makers() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+        let maker = Pubkey::new_unique();
+        let mut open_orders = OpenOrders {
+            owner: maker,
+            ..Default::default()
+        };
+
+        // Handlers lock the deposit once the order rests.
+        let mut sell_nos = Order::new_test_of(maker, 10, 4);
+        sell_nos.set_uses_open_orders(true);
+        book.new_limit(
+            sell_nos,
+            GTC,
+            false,
+            &mut rb,
+            &mut rb_crank,
+            Some(&mut open_orders),
+            &CLOCK,
+        )
+        .unwrap();
+        open_orders.lock(false, 4);
+
+        // Fills are paid through the crank and release the base they consumed.
+        let buy_nos = Order::new_test(10, 3);
+        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        let crank = rb_crank.remove_head().unwrap();
+        assert_eq!((crank.get_quantity(), crank.get_released()), (30, 3));
+        assert!(rb_crank.is_empty());
+        open_orders.credit_crank(false, crank.get_quantity(), crank.get_released());
+        assert_eq!(open_orders.base_locked, 1);
+        assert_eq!(open_orders.quote_free, 30);
+
+        // The maker cancelling with its open orders at hand is refunded directly.
+        let head = book.asks.head;
+        book.asks
+            .cancel_order(
+                head,
+                false,
+                CancelReason::Maker,
+                &mut rb_crank,
+                Some(&mut open_orders),
+                &CLOCK,
+            )
+            .unwrap();
+
+        assert_eq!(open_orders.base_locked, 0);
+        assert_eq!(open_orders.base_free, 1);
+        assert!(rb_crank.is_empty());
+
+        // Anyone else refunds through the crank.
+        let mut sell_nos = Order::new_test_of(maker, 10, 2);
+        sell_nos.set_uses_open_orders(true);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        let head = book.asks.head;
+        book.asks
+            .cancel_order(
+                head,
+                false,
+                CancelReason::Expired,
+                &mut rb_crank,
+                Some(&mut OpenOrders::default()),
+                &CLOCK,
+            )
+            .unwrap();
+
+        let crank = rb_crank.remove_head().unwrap();
+        assert_eq!((crank.get_quantity(), crank.get_released()), (2, 0));
+    }
+
+    #[test]
+    fn it_should_prevent_self_trades() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+        let maker = Pubkey::new_unique();
+
+        for limit in [10, 11] {
+            let sell_nos = Order::new_test_of(maker, limit, 2);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+
+        let mut buy_nos = Order::new_test_of(maker, 11, 3);
+        buy_nos.set_self_trade_behavior(SelfTradeBehavior::AbortTransaction);
+        assert!(book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .is_err());
+
+        // Decrement cancels the best ask and reduces the next one.
+        let buy_nos = Order::new_test_of(maker, 11, 3);
+        let buy_order = book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        assert!(buy_order.is_filled());
+        assert_eq!(buy_order.get_cum_qty(), 0);
+        assert_eq!(book.ask_min, 11);
+        assert_eq!(
+            book.asks.orders[book.asks.head as usize]
+                .order
+                .get_leaves_qty(),
+            1
+        );
+        assert_eq!(rb.filled_exec_reports[0].quantity, 0);
+        assert_eq!(rb_crank.len(), 2);
+
+        // Cancel provide removes the resting order and rests the new one.
+        let mut buy_nos = Order::new_test_of(maker, 11, 3);
+        buy_nos.set_self_trade_behavior(SelfTradeBehavior::CancelProvide);
+        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        assert!(book.asks.is_empty());
+        assert_eq!(book.bid_max, 11);
+        assert_eq!(rb_crank.len(), 3);
+    }
+
+    #[test]
+    fn it_should_spend_at_most_the_quote_budget() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        for limit in [100, 200] {
+            let sell_nos = Order::new_test(limit, 3);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+
+        // 250 buys two at 100 and none at 200, with 1% taker fee.
+        let mut buy_nos = Order::new_test(u64::MAX, u64::MAX);
+        buy_nos.set_fees(0, 100);
+        buy_nos.set_quote_budget(Some(250));
+        let buy_order = book
+            .new_limit(
+                buy_nos,
+                OrderType::MKT,
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        assert_eq!(buy_order.get_cum_qty(), 2);
+        assert_eq!(buy_order.get_cum_cost(), 200);
+        assert_eq!(buy_order.get_cum_fee(), 2);
+        assert_eq!(buy_order.get_leaves_qty(), 0);
+        assert!(book.bids.is_empty());
+        assert_eq!(book.ask_min, 100);
+
+        // Market sell sweeps no bids and never rests.
+        let sell_nos = Order::new_test(0, 5);
+        let sell_order = book
+            .new_limit(
+                sell_nos,
+                OrderType::MKT,
+                false,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        assert_eq!(sell_order.get_cum_qty(), 0);
+        assert_eq!(
+            book.asks.orders[book.asks.head as usize]
+                .order
+                .get_leaves_qty(),
+            1
+        );
+
+        // Market buy sweeps all asks.
+        let buy_nos = Order::new_test(u64::MAX, 10);
+        let buy_order = book
+            .new_limit(
+                buy_nos,
+                OrderType::MKT,
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        assert_eq!(buy_order.get_cum_qty(), 4);
+        assert_eq!(buy_order.get_cum_cost(), 100 + 3 * 200);
+        assert!(book.asks.is_empty());
+        assert!(book.bids.is_empty());
+    }
+
+    #[test]
+    fn it_should_skip_and_prune_expired_orders() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+        const GTT: OrderType = OrderType::GTT;
+        let at = |unix_timestamp| Clock {
+            unix_timestamp,
+            ..CLOCK
+        };
+
+        // Placed and matched at 2.
+        for (limit, expiry) in [(10, 1), (11, 3)] {
+            let mut sell_nos = Order::new_test(limit, 2);
+            sell_nos.set_expiry(Some(expiry));
+            book.new_limit(sell_nos, GTT, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+
+        let buy_nos = Order::new_test(11, 1);
+        let buy_order = book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        assert_eq!(buy_order.get_cum_cost(), 11);
+        assert_eq!(book.ask_min, 11);
+        assert_eq!(rb_crank.len(), 2); // refund and payout
+
+        for (limit, expiry) in [(8, 2), (9, 0)] {
+            let mut buy_nos = Order::new_test(limit, 2);
+            buy_nos.set_expiry(Some(expiry));
+            book.new_limit(buy_nos, GTT, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+
+        assert_eq!(book.prune_expired(1, &mut rb_crank, &at(2)).unwrap(), 0);
+        assert_eq!(book.prune_expired(3, &mut rb_crank, &at(2)).unwrap(), 1);
+        assert_eq!(book.bid_max, 9);
+        assert_eq!(book.bids.orders[book.bids.tail as usize].order.limit, 9);
+        assert_eq!(book.prune_expired(3, &mut rb_crank, &at(3)).unwrap(), 1);
+        assert!(book.asks.is_empty());
+        assert_eq!(rb_crank.len(), 4);
+    }
+
+    #[test]
+    fn it_should_reject_or_slide_crossing_maker_only_orders() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        let sell_nos = Order::new_test(10, 2);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        let buy_nos = Order::new_test(8, 2);
+        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        let last_order_id = book.last_order_id;
+
+        // Rejected before the book or the queues are touched.
+        let buy_nos = Order::new_test(10, 1);
+        assert!(book
+            .new_limit(
+                buy_nos,
+                OrderType::MO,
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .is_err());
+        assert_eq!(book.last_order_id, last_order_id);
+        assert_eq!(
+            book.asks.orders[book.asks.head as usize]
+                .order
+                .get_leaves_qty(),
+            2
+        );
+        assert_eq!(rb_crank.len(), 0);
+
+        let buy_nos = Order::new_test(12, 1);
+        let buy_order = book
+            .new_limit(
+                buy_nos,
+                OrderType::MOS,
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        assert_eq!(buy_order.limit, 9);
+        assert_eq!(buy_order.get_cum_qty(), 0);
+        assert_eq!(book.bid_max, 9);
+
+        let sell_nos = Order::new_test(5, 1);
+        let sell_order = book
+            .new_limit(
+                sell_nos,
+                OrderType::MOS,
+                false,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        assert_eq!(sell_order.limit, 10);
+        assert_eq!(book.ask_min, 10);
+        assert_eq!(rb_crank.len(), 0);
+
+        // Amends re-apply the maker-only semantics of the resting order.
+        let head = book.bids.head;
+        let removed_order = book.bids.remove_order(head);
+        book.refresh_best_offers();
+        assert_eq!(removed_order.get_order_type(), OrderType::MOS);
+        let amended = book
+            .new_limit(
+                removed_order.amend(11, 1, CLOCK.unix_timestamp).unwrap(),
+                removed_order.get_order_type(),
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        assert_eq!(amended.limit, 9);
+        assert_eq!(amended.get_cum_qty(), 0);
+        assert_eq!(rb_crank.len(), 0);
+    }
+
+    #[test]
+    fn it_should_kill_fok_orders_without_a_trace() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+        const FOK: OrderType = OrderType::FOK;
+
+        for limit in [10, 11, 12] {
+            let sell_nos = Order::new_test(limit, 2);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+
+        let buy_nos = Order::new_test(11, 5);
+        assert_eq!(book.fillable_qty(&buy_nos, true, CLOCK.unix_timestamp), 4);
+        assert!(book
+            .new_limit(buy_nos, FOK, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .is_err());
+        assert_eq!(book.ask_min, 10);
+        assert_eq!(
+            book.asks.orders[book.asks.head as usize]
+                .order
+                .get_leaves_qty(),
+            2
+        );
+        assert!(book.bids.is_empty());
+        assert_eq!(rb.filled_exec_reports[0].quantity, 0);
+        assert_eq!(rb_crank.len(), 0);
+
+        let buy_nos = Order::new_test(12, 5);
+        let buy_order = book
+            .new_limit(buy_nos, FOK, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        assert!(buy_order.is_filled());
+        assert_eq!(buy_order.get_cum_cost(), 2 * 10 + 2 * 11 + 12);
+        assert_eq!(book.ask_min, 12);
+
+        // Own orders are decremented without a fill and never count.
+        let maker = Pubkey::new_unique();
+        let sell_nos = Order::new_test_of(maker, 12, 2);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        let buy_nos = Order::new_test_of(maker, 12, 2);
+        assert_eq!(book.fillable_qty(&buy_nos, true, CLOCK.unix_timestamp), 1);
+        assert!(book
+            .new_limit(buy_nos, FOK, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .is_err());
+        assert_eq!(
+            book.asks.orders[book.asks.head as usize]
+                .order
+                .get_leaves_qty(),
+            1
+        );
+    }
+
+    #[test]
+    fn it_should_charge_maker_and_taker_fees() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        let mut sell_nos = Order::new_test(1000, 4);
+        sell_nos.set_fees(25, 50);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        let mut buy_nos = Order::new_test(1000, 3);
+        buy_nos.set_fees(25, 50);
+        let buy_order = book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        assert_eq!(rb.filled_exec_reports[0].maker_fee, 8);
+        assert_eq!(rb.filled_exec_reports[0].taker_fee, 15);
+        assert_eq!(buy_order.get_cum_fee(), 15);
+        assert_eq!(rb_crank.cranks[0].get_quantity(), 3000 - 8);
+        assert_eq!(book.fees_accrued, 23);
+
+        let mut buy_nos = Order::new_test(1000, 1);
+        buy_nos.set_fees(0, 0);
+        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        // Partial maker fees add up to the fee on the whole order.
+        assert_eq!(rb.filled_exec_reports[1].maker_fee, 2);
+        assert_eq!(rb.filled_exec_reports[1].taker_fee, 0);
+        assert_eq!(book.fees_accrued, 25);
+    }
+
+    #[test]
+    fn it_should_fail_fills_that_overflow() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        let limit = u64::MAX / 2;
+        let sell_nos = Order::new_test(limit, 3);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        // The cost of the fill does not fit.
+        let buy_nos = Order::new_test(limit, 3);
+        let err = book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::FillOverflow.into());
+
+        // Neither do the fees accrued.
+        book.fees_accrued = u64::MAX;
+        let mut buy_nos = Order::new_test(limit, 1);
+        buy_nos.set_fees(0, 1);
+        let err = book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::FillOverflow.into());
+    }
+
+    #[test]
+    fn it_should_refund_maker_fee_deposit_on_eviction() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+        book.overflow_policy = OverflowPolicy::Evict as u8;
+
+        let mut buy_nos = Order::new_test(1000, 4);
+        buy_nos.set_fees(25, 50);
+        let buy_order = book
+            .new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        assert_eq!(buy_order.get_leaves_deposit(true), Some(4000 + 10));
+
+        for i in 1..CAPACITY {
+            let buy_nos = Order::new_test(1000 + i as u64, 1);
+            book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+        let buy_nos = Order::new_test(5000, 1);
+        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+
+        // Worst bid is cancelled and its deposit including fees refunded.
+        assert_eq!(rb_crank.cranks[0].get_quantity(), 4010);
+        assert_eq!(book.fees_accrued, 0);
+    }
+
+    #[test]
+    fn it_should_apply_the_overflow_policy_of_full_sides() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        for i in 0..CAPACITY {
+            let sell_nos = Order::new_test(100 + i as u64, 1);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+        let worst = 100 + CAPACITY as u64 - 1;
+
+        // Rejected by default, nothing is cancelled.
+        let sell_nos = Order::new_test(50, 1);
+        assert!(book
+            .new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .is_err());
+        assert_eq!(rb_crank.len(), 0);
+
+        book.overflow_policy = OverflowPolicy::Evict as u8;
+        book.tick_size = 2;
+        book.min_eviction_ticks = 5;
+
+        let sell_nos = Order::new_test(worst - 8, 1);
+        assert!(book
+            .new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .is_err());
+
+        let sell_nos = Order::new_test(worst - 10, 1);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .unwrap();
+        assert_eq!(rb_crank.len(), 1);
+        assert_eq!(
+            book.asks.orders[book.asks.tail as usize].order.limit,
+            worst - 1
+        );
+        assert_eq!(book.asks.depth(usize::MAX).len(), CAPACITY as usize - 1);
+    }
+
+    #[test]
+    fn it_should_keep_inserting_after_an_eviction() {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(3);
+        book.overflow_policy = OverflowPolicy::Evict as u8;
+
+        for limit in [10, 11, 12, 5, 4] {
+            let sell_nos = Order::new_test(limit, 1);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+        assert_eq!(book.asks.depth(usize::MAX), vec![(4, 1), (5, 1), (10, 1)]);
+        assert_eq!(rb_crank.len(), 2);
+
+        // A cancel frees a slot, the next order takes it without evicting.
+        let tail = book.asks.tail;
+        book.asks
+            .cancel_order(
+                tail,
+                false,
+                CancelReason::Maker,
+                &mut rb_crank,
+                None,
+                &CLOCK,
+            )
+            .unwrap();
+        for limit in [7, 3] {
+            let sell_nos = Order::new_test(limit, 1);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+        assert_eq!(book.asks.depth(usize::MAX), vec![(3, 1), (4, 1), (5, 1)]);
+        assert_eq!(rb_crank.len(), 4);
+    }
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<Vec<u8>>> = RefCell::new(Vec::new());
+    }
+
+    /// Records the events emitted on the test thread instead of logging them.
+    struct EventStubs;
+
+    impl SyscallStubs for EventStubs {
+        fn sol_log_data(&self, fields: &[&[u8]]) {
+            EVENTS.with(|events| {
+                events
+                    .borrow_mut()
+                    .extend(fields.iter().map(|f| f.to_vec()))
+            });
+        }
+    }
+
+    /// Starts recording the events of the test thread from scratch.
+    fn capture_events() {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(EventStubs));
+        });
+        EVENTS.with(|events| events.borrow_mut().clear());
+    }
+
+    /// Events of one type recorded since `capture_events`, oldest first.
+    fn events<T: Discriminator + AnchorDeserialize>() -> Vec<T> {
+        EVENTS.with(|events| {
+            events
+                .borrow()
+                .iter()
+                .filter(|data| data[..8] == T::DISCRIMINATOR)
+                .map(|data| T::try_from_slice(&data[8..]).unwrap())
+                .collect()
+        })
+    }
+
+    #[test]
+    fn it_should_emit_events_with_the_instrmt() {
+        capture_events();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(2);
+        let instrmt = Pubkey::new_unique();
+        book.set_instrmt(instrmt);
+        let clock = Clock { slot: 7, ..CLOCK };
+
+        let sell_nos = Order::new_test(10, 3);
+        book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &clock)
+            .unwrap();
+        let buy_nos = Order::new_test(10, 1);
+        book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &clock)
+            .unwrap();
+
+        let accepted = events::<OrderAccepted>();
+        assert_eq!(accepted.len(), 2);
+        assert!(accepted
+            .iter()
+            .all(|event| event.instrmt == instrmt && event.slot == 7));
+        assert_eq!((accepted[0].order_id, accepted[0].qty), (1, 3));
+        assert_eq!(accepted[1].order_type, GTC);
+
+        let partially_filled = events::<OrderPartiallyFilled>();
+        assert_eq!(partially_filled.len(), 1);
+        assert_eq!(partially_filled[0].instrmt, instrmt);
+        assert_eq!(partially_filled[0].order_id, 1);
+        assert_eq!(
+            (
+                partially_filled[0].filled_qty,
+                partially_filled[0].leaves_qty
+            ),
+            (1, 2)
+        );
+        assert_eq!(partially_filled[0].slot, 7);
+
+        let filled = events::<OrderFilled>();
+        assert_eq!(filled.len(), 1);
+        assert_eq!(filled[0].instrmt, instrmt);
+        assert_eq!(
+            (filled[0].order_id, filled[0].price, filled[0].filled_qty),
+            (2, 10, 1)
+        );
+        assert_eq!(filled[0].slot, 7);
+
+        // Rejected without an order id.
+        let buy_nos = Order::new_test(10, 1);
+        assert!(book
+            .new_limit(
+                buy_nos,
+                OrderType::MO,
+                true,
+                &mut rb,
+                &mut rb_crank,
+                None,
+                &clock,
+            )
+            .is_err());
+        let rejected = events::<OrderRejected>();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].instrmt, instrmt);
+        assert_eq!(rejected[0].reason, RejectReason::MakerOnlyCrossed);
+        assert_eq!(
+            (rejected[0].limit, rejected[0].qty, rejected[0].slot),
+            (10, 1, 7)
+        );
+
+        let buy_nos = Order::new_test(9, 1);
+        book.new_limit(
+            buy_nos,
+            OrderType::IOC,
+            true,
+            &mut rb,
+            &mut rb_crank,
+            None,
+            &clock,
+        )
+        .unwrap();
+        let cancelled = events::<OrderCancelled>();
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0].instrmt, instrmt);
+        assert_eq!((cancelled[0].order_id, cancelled[0].leaves_qty), (3, 1));
+        assert_eq!(cancelled[0].reason, CancelReason::Unfilled);
+        assert_eq!(cancelled[0].slot, 7);
+
+        // The partially filled order is the worst of the full asks.
+        book.overflow_policy = OverflowPolicy::Evict as u8;
+        for limit in [9, 8] {
+            let sell_nos = Order::new_test(limit, 1);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &clock)
+                .unwrap();
+        }
+        let cancelled = events::<OrderCancelled>();
+        assert_eq!(cancelled.len(), 2);
+        assert_eq!(cancelled[1].instrmt, instrmt);
+        assert_eq!((cancelled[1].order_id, cancelled[1].filled_qty), (1, 1));
+        assert_eq!(cancelled[1].reason, CancelReason::Evicted);
+
+        let evicted = events::<OrderEvicted>();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].instrmt, instrmt);
+        assert_eq!((evicted[0].order_id, evicted[0].evicted_by), (1, 5));
+        assert_eq!((evicted[0].filled_qty, evicted[0].leaves_qty), (1, 2));
+        assert_eq!((evicted[0].limit, evicted[0].slot), (10, 7));
+    }
+
+    #[test]
+    fn it_should_size_sides_from_the_account_data() {
+        let mut book_data = vec![0u64; (Book::space(3) + 100) / 8];
+        let mut rb_data = Vec::new();
+        let mut rb = RingBufferFilledExecReport::new(&mut rb_data, REPORT_CAPACITY);
+        let mut rb_crank_data = Vec::new();
+        let mut rb_crank = RingBufferCrank::new(&mut rb_crank_data, CRANK_CAPACITY);
+
+        let mut book = Book::init(bytemuck::cast_slice_mut(&mut book_data)).unwrap();
+        assert_eq!(book.capacity, 3);
+        assert_eq!(book.asks.capacity(), 3);
+        for limit in [10, 11, 12] {
+            let sell_nos = Order::new_test(limit, 1);
+            book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                .unwrap();
+        }
+        let sell_nos = Order::new_test(13, 1);
+        assert!(book
+            .new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+            .is_err());
+
+        let data: &mut [u8] = bytemuck::cast_slice_mut(&mut book_data);
+        let book = Book::load_mut(data).unwrap();
+        assert_eq!(book.asks.depth(usize::MAX), vec![(10, 1), (11, 1), (12, 1)]);
+
+        let data: &mut [u8] = bytemuck::cast_slice_mut(&mut book_data);
+        assert!(Book::load_mut(&mut data[..Book::space(3) - 8]).is_err());
+        assert!(Book::init(&mut data[..Book::space(1) - 8]).is_err());
+    }
+
+    /// Sum of leaves deposits resting on a side.
+    fn resting_deposits(side: &Side, is_buy: bool) -> u64 {
+        let mut deposits = 0;
+        if side.is_empty() {
+            return deposits;
+        }
+        let mut pos = side.head;
+        loop {
+            deposits += side.orders[pos as usize]
+                .order
+                .get_leaves_deposit(is_buy)
+                .unwrap();
+            pos = match side.next_order(pos) {
+                None => return deposits,
+                Some(next_pos) => next_pos,
+            };
+        }
+    }
+
+    /// Levels aggregated by walking the orders, each price must be one run.
+    fn walk_levels(side: &Side, is_buy: bool) -> Vec<(u64, u64)> {
+        let mut levels: Vec<(u64, u64)> = Vec::new();
+        if side.is_empty() {
+            return levels;
+        }
+        let mut pos = side.head;
+        loop {
+            let order = &side.orders[pos as usize].order;
+            match levels.last_mut() {
+                Some((price, qty)) if *price == order.limit => *qty += order.get_leaves_qty(),
+                Some((price, _)) => {
+                    assert!(if is_buy {
+                        *price > order.limit
+                    } else {
+                        *price < order.limit
+                    });
+                    levels.push((order.limit, order.get_leaves_qty()));
+                }
+                None => levels.push((order.limit, order.get_leaves_qty())),
+            }
+            pos = match side.next_order(pos) {
+                None => return levels,
+                Some(next_pos) => next_pos,
+            };
+        }
+    }
+
+    #[quickcheck]
+    fn it_should_keep_price_levels_in_sync(ops: Vec<(bool, u8, u8, Option<u8>)>) -> bool {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+
+        for (is_buy, limit, qty, cancel) in ops {
+            match cancel {
+                Some(pos) => {
+                    let side = if is_buy {
+                        &mut book.bids
+                    } else {
+                        &mut book.asks
+                    };
+                    let pos = pos as u16 % 64;
+                    if !side.is_tombstone(pos) {
+                        side.cancel_order(
+                            pos,
+                            is_buy,
+                            CancelReason::Maker,
+                            &mut rb_crank,
+                            None,
+                            &CLOCK,
+                        )
+                        .unwrap();
+                        book.refresh_best_offers();
+                    }
+                }
+                None => {
+                    let new_order = Order::new_test(limit as u64 % 30 + 1, qty as u64 % 5 + 1);
+                    book.new_limit(new_order, GTC, is_buy, &mut rb, &mut rb_crank, None, &CLOCK)
+                        .unwrap();
+                }
+            }
+            while rb_crank.remove_head().is_some() {}
+
+            assert_eq!(book.bids.depth(usize::MAX), walk_levels(&book.bids, true));
+            assert_eq!(book.asks.depth(usize::MAX), walk_levels(&book.asks, false));
+        }
+        true
+    }
+
+    #[quickcheck]
+    fn it_should_keep_vaults_equal_to_obligations(orders: Vec<(bool, u8, u8, bool)>) -> bool {
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
+        let (base_vault, quote_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        book.set_base_vault(base_vault);
+        book.set_quote_vault(quote_vault);
+
+        let (mut base_balance, mut quote_balance) = (0u64, 0u64);
+        let mut base_bought: HashMap<Pubkey, u64> = HashMap::new();
+        let mut base_credited: HashMap<Pubkey, u64> = HashMap::new();
+
+        for (is_buy, limit, qty, is_ioc) in orders {
+            let limit = (limit as u64 % 20 + 1) * 100;
+            let mut new_order = Order::new_test(limit, qty as u64 % 10 + 1);
+            new_order.payout_acc = new_order.maker;
+            new_order.set_fees(10, 30);
+            let order_type = if is_ioc { OrderType::IOC } else { GTC };
+
+            let last_seen = rb.seq_num;
+            let order = book
+                .new_limit(
+                    new_order,
+                    order_type,
+                    is_buy,
+                    &mut rb,
+                    &mut rb_crank,
+                    None,
+                    &CLOCK,
+                )
+                .unwrap();
+
+            let (deposit, proceeds) = order.get_settlement(is_buy).unwrap();
+            match is_buy {
+                true => {
+                    quote_balance += deposit;
+                    base_balance -= proceeds;
+                }
+                false => {
+                    base_balance += deposit;
+                    quote_balance -= proceeds;
+                }
+            }
+
+            for filled_exec_report in rb.reports_since(last_seen).unwrap() {
+                if filled_exec_report.side == BookSide::Sell as u8 {
+                    *base_bought.entry(filled_exec_report.maker).or_default() +=
+                        filled_exec_report.quantity;
+                }
+            }
+
+            while let Some(crank) = rb_crank.remove_head() {
+                if crank.get_vault() == base_vault {
+                    base_balance -= crank.get_quantity();
+                    *base_credited.entry(crank.get_token_account()).or_default() +=
+                        crank.get_quantity();
+                } else {
+                    quote_balance -= crank.get_quantity();
+                }
+            }
+        }
+
+        base_bought.retain(|_, qty| *qty > 0);
+        base_balance == resting_deposits(&book.asks, false)
+            && quote_balance == resting_deposits(&book.bids, true) + book.fees_accrued
+            && base_bought == base_credited
     }
 
     #[quickcheck]
@@ -451,18 +1952,11 @@ mod test {
         mut buy: Vec<(u16, u32)>,
         mut sell: Vec<(u16, u32)>,
     ) -> TestResult {
-        buy = buy
-            .into_iter()
-            .filter(|(x, y)| *x != 0 && *y != 0)
-            .collect();
-        sell = sell
-            .into_iter()
-            .filter(|(x, y)| *x != 0 && *y != 0)
-            .collect();
+        buy.retain(|(x, y)| *x != 0 && *y != 0);
+        sell.retain(|(x, y)| *x != 0 && *y != 0);
 
-        let mut book = Book::new();
-        let mut rb = RingBufferFilledExecReport::new();
-        let mut rb_crank = RingBufferCrank::new();
+        let mut accounts = Accounts::default();
+        let (mut book, mut rb, mut rb_crank) = accounts.load(CAPACITY);
         let mut clone_buy = buy.clone();
         let mut clone_sell = sell.clone();
 
@@ -471,7 +1965,7 @@ mod test {
         let mut sort_buy_rank = 0;
         let mut sort_sell_rank = 0;
         loop {
-            if clone_buy.len() > 0 {
+            if !clone_buy.is_empty() {
                 let (bid_price, mut bid_size) = clone_buy.remove(0);
                 for (ask_price, ask_size, _) in &mut sort_sell.iter_mut() {
                     if bid_price >= *ask_price {
@@ -496,7 +1990,7 @@ mod test {
                     |(ax, _, cx), (ay, _, cy)| if ax == ay { cx.cmp(cy) } else { ay.cmp(ax) },
                 );
             }
-            if clone_sell.len() > 0 {
+            if !clone_sell.is_empty() {
                 let (ask_price, mut ask_size) = clone_sell.remove(0);
                 for (bid_price, bid_size, _) in &mut sort_buy.iter_mut() {
                     if ask_price <= *bid_price {
@@ -522,24 +2016,26 @@ mod test {
                 );
             }
 
-            if clone_buy.len() == 0 && clone_sell.len() == 0 {
+            if clone_buy.is_empty() && clone_sell.is_empty() {
                 break;
             }
         }
 
         loop {
-            if buy.len() > 0 {
+            if !buy.is_empty() {
                 let (bid_price, bid_size) = buy.remove(0);
                 let buy_nos = Order::new_test(bid_price as u64, bid_size as u64);
-                book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank);
+                book.new_limit(buy_nos, GTC, true, &mut rb, &mut rb_crank, None, &CLOCK)
+                    .unwrap();
             }
-            if sell.len() > 0 {
+            if !sell.is_empty() {
                 let (ask_price, ask_size) = sell.remove(0);
                 let sell_nos = Order::new_test(ask_price as u64, ask_size as u64);
-                book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank);
+                book.new_limit(sell_nos, GTC, false, &mut rb, &mut rb_crank, None, &CLOCK)
+                    .unwrap();
             }
 
-            if buy.len() == 0 && sell.len() == 0 {
+            if buy.is_empty() && sell.is_empty() {
                 break;
             }
         }
@@ -1,27 +1,64 @@
+use std::{
+    mem::size_of,
+    ops::{Deref, DerefMut},
+};
+
 use anchor_lang::prelude::*;
 
-const CRANK_SIZE: u16 = 4096;
+use crate::errors::ErrorCode;
 
 // Ring Buffer Crank. Fifo, first-in-first-out
+//
+// Empty when head equals next. One element is kept free to tell a full
+// buffer apart from an empty one.
 #[account(zero_copy)]
-pub struct RingBufferCrank {
+pub struct RingBufferCrankHeader {
     pub instrmt_grp: Pubkey, // Instrument group rb-crank belongs to.
-    pub cranks: [Crank; CRANK_SIZE as usize],
-    pub head: u16, // First element inserted.
-    pub next: u16, // Next avail element.
-    pub padding: [u8; 4],
+    pub head: u16,           // First element inserted.
+    pub next: u16,           // Next avail element.
+    pub capacity: u16,       // Max number of cranks waiting, sized from the account.
+    pub padding: [u8; 2],
+}
+
+impl RingBufferCrankHeader {
+    /// Number of cranks waiting to be processed.
+    pub fn len(&self) -> u16 {
+        let slots = self.slots() as u32;
+        ((self.next as u32 + slots - self.head as u32) % slots) as u16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head == self.next
+    }
+
+    pub fn is_full(&self) -> bool {
+        (self.next + 1) % self.slots() == self.head
+    }
+
+    /// Max number of cranks waiting to be processed.
+    pub fn capacity(&self) -> u16 {
+        self.capacity
+    }
+
+    fn slots(&self) -> u16 {
+        self.capacity + 1
+    }
+}
+
+/// Cranks of the ring buffer over the account data, as many slots as the
+/// capacity plus the one kept free.
+pub struct RingBufferCrank<'a> {
+    header: &'a mut RingBufferCrankHeader,
+    pub cranks: &'a mut [Crank],
 }
 
 #[cfg(test)]
-impl RingBufferCrank {
-    pub fn new() -> Self {
-        Self {
-            instrmt_grp: Pubkey::default(),
-            cranks: [Crank::new_empty(); CRANK_SIZE as usize],
-            next: 1,
-            head: 0,
-            padding: [0u8; 4],
-        }
+impl<'a> RingBufferCrank<'a> {
+    /// Ring buffer with a capacity of cranks over a zeroed buffer.
+    pub fn new(data: &'a mut Vec<u64>, capacity: u16) -> Self {
+        data.resize(Self::space(capacity) / 8, 0);
+        let data: &mut [u8] = bytemuck::cast_slice_mut(data);
+        Self::init(data).unwrap()
     }
 }
 #[cfg(test)]
@@ -32,47 +69,95 @@ impl Crank {
             vault: Pubkey::default(),
             token_acc: Pubkey::default(),
             quantity: 0,
+            released: 0,
         }
     }
 }
 
-impl RingBufferCrank {
+impl<'a> RingBufferCrank<'a> {
+    /// Sizes a new ring buffer from the length of the account data.
+    pub fn init(data: &'a mut [u8]) -> Result<Self> {
+        let slots = data.len().saturating_sub(Self::space(0)) / Crank::space() + 1;
+        let capacity = slots.saturating_sub(1).min(u16::MAX as usize - 1) as u16;
+        require!(capacity > 0, ErrorCode::InvalidCapacity);
+        let header: &mut RingBufferCrankHeader =
+            bytemuck::try_from_bytes_mut(&mut data[8..8 + size_of::<RingBufferCrankHeader>()])
+                .map_err(|_| ErrorCode::InvalidCapacity)?;
+        header.capacity = capacity;
+        Self::load_mut(data)
+    }
+
+    /// Ring buffer over the account data, including the discriminator.
+    pub fn load_mut(data: &'a mut [u8]) -> Result<Self> {
+        require!(
+            data.len() >= 8 + size_of::<RingBufferCrankHeader>(),
+            ErrorCode::InvalidCapacity
+        );
+        let (header, cranks) = data[8..].split_at_mut(size_of::<RingBufferCrankHeader>());
+        let header: &mut RingBufferCrankHeader =
+            bytemuck::try_from_bytes_mut(header).map_err(|_| ErrorCode::InvalidCapacity)?;
+        let slots = header.slots() as usize;
+        require!(
+            header.capacity > 0 && cranks.len() >= slots * Crank::space(),
+            ErrorCode::InvalidCapacity
+        );
+        Ok(Self {
+            header,
+            cranks: bytemuck::try_cast_slice_mut(&mut cranks[..slots * Crank::space()])
+                .map_err(|_| ErrorCode::InvalidCapacity)?,
+        })
+    }
+
+    /// Queue a transfer of quantity from vault to token account, or a credit
+    /// of the open orders in its place releasing the deposit a fill consumed.
+    ///
+    /// Nothing to transfer or release for a zero quantity and release, hence
+    /// nothing is queued.
     pub fn insert(
         &mut self,
         vault: Pubkey,
         token_account: Pubkey,
-        is_buy: bool,
         maker: Pubkey,
         quantity: u64,
-        limit: u64,
-    ) {
-        if self.head == self.next {
-            panic!("rb-crank filled up. Crank faster to accept new orders!");
+        released: u64,
+    ) -> Result<()> {
+        if quantity == 0 && released == 0 {
+            return Ok(());
         }
+        require!(!self.is_full(), ErrorCode::RbCrankFull);
 
-        let crank = match is_buy {
-            true => Crank::new(
-                maker,
-                vault,
-                token_account,
-                quantity.checked_add(limit).unwrap(),
-            ),
-            false => Crank::new(maker, vault, token_account, quantity),
-        };
+        self.cranks[self.header.next as usize] =
+            Crank::new(maker, vault, token_account, quantity, released);
+        self.header.next = (self.header.next + 1) % self.header.slots();
+        Ok(())
+    }
 
-        self.cranks[self.next as usize] = crank;
-        self.next = (self.next + 1) % CRANK_SIZE
+    pub fn remove_head(&mut self) -> Option<Crank> {
+        if self.is_empty() {
+            return None;
+        }
+        let head = self.cranks[self.header.head as usize];
+        self.cranks[self.header.head as usize].clear();
+        self.header.head = (self.header.head + 1) % self.header.slots();
+        Some(head)
     }
 
-    pub fn remove_head(&mut self) -> Crank {
-        let head = self.cranks[self.head as usize].clone();
-        self.cranks[self.head as usize].clear();
-        self.head = (self.head + 1) & CRANK_SIZE;
-        head
+    pub fn space(capacity: u16) -> usize {
+        8 + size_of::<RingBufferCrankHeader>() + (capacity as usize + 1) * Crank::space()
     }
+}
 
-    pub fn space() -> usize {
-        CRANK_SIZE as usize * Crank::space() + 1 + 7
+impl Deref for RingBufferCrank<'_> {
+    type Target = RingBufferCrankHeader;
+
+    fn deref(&self) -> &Self::Target {
+        self.header
+    }
+}
+
+impl DerefMut for RingBufferCrank<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.header
     }
 }
 
@@ -80,21 +165,29 @@ impl RingBufferCrank {
 pub struct Crank {
     maker: Pubkey,     // Maker.
     vault: Pubkey,     // Mint to transfer to maker.
-    token_acc: Pubkey, // Mint to transfer to maker.
+    token_acc: Pubkey, // Mint to transfer to maker, or open orders to credit.
     quantity: u64,     // Total quantity filled.
+    released: u64,     // Deposit consumed by a fill of open orders, 0 for refunds.
 }
 
 impl Crank {
-    pub fn new(maker: Pubkey, vault: Pubkey, token_acc: Pubkey, quantity: u64) -> Self {
+    pub fn new(
+        maker: Pubkey,
+        vault: Pubkey,
+        token_acc: Pubkey,
+        quantity: u64,
+        released: u64,
+    ) -> Self {
         Self {
             maker,
             vault,
             quantity,
             token_acc,
+            released,
         }
     }
     pub fn is_empty(&self) -> bool {
-        self.quantity == 0
+        self.quantity == 0 && self.released == 0
     }
 
     pub fn get_maker(&self) -> Pubkey {
@@ -113,14 +206,93 @@ impl Crank {
         self.quantity
     }
 
+    pub fn get_released(&self) -> u64 {
+        self.released
+    }
+
     pub fn clear(&mut self) {
         self.maker = Pubkey::default();
         self.vault = Pubkey::default();
         self.token_acc = Pubkey::default();
         self.quantity = 0;
+        self.released = 0;
     }
 
     pub fn space() -> usize {
-        32 * 2 + 8
+        32 * 3 + 8 * 2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::VecDeque;
+
+    use anchor_lang::prelude::Pubkey;
+    use quickcheck_macros::quickcheck;
+
+    use super::RingBufferCrank;
+
+    const CAPACITY: u16 = 4095;
+
+    #[test]
+    fn it_should_reject_insert_when_full() {
+        let mut rb_crank_data = Vec::new();
+        let mut rb_crank = RingBufferCrank::new(&mut rb_crank_data, CAPACITY);
+        let key = Pubkey::default();
+
+        for i in 0..rb_crank.capacity() {
+            rb_crank.insert(key, key, key, i as u64 + 1, 0).unwrap();
+        }
+        assert!(rb_crank.is_full());
+        assert_eq!(rb_crank.len(), rb_crank.capacity());
+        assert!(rb_crank.insert(key, key, key, 1, 0).is_err());
+
+        assert_eq!(rb_crank.remove_head().unwrap().get_quantity(), 1);
+        assert!(rb_crank.insert(key, key, key, 1, 0).is_ok());
+    }
+
+    #[test]
+    fn it_should_skip_zero_quantity() {
+        let mut rb_crank_data = Vec::new();
+        let mut rb_crank = RingBufferCrank::new(&mut rb_crank_data, CAPACITY);
+        let key = Pubkey::default();
+
+        rb_crank.insert(key, key, key, 0, 0).unwrap();
+        assert!(rb_crank.is_empty());
+        assert!(rb_crank.remove_head().is_none());
+    }
+
+    #[quickcheck]
+    fn it_should_be_fifo_across_wraparound(start: u16, ops: Vec<(bool, u8)>) -> bool {
+        let mut rb_crank_data = Vec::new();
+        let mut rb_crank = RingBufferCrank::new(&mut rb_crank_data, CAPACITY);
+        rb_crank.head = start % (CAPACITY + 1);
+        rb_crank.next = start % (CAPACITY + 1);
+        let key = Pubkey::default();
+
+        let mut model = VecDeque::new();
+        let mut quantity = 0;
+        for (is_insert, n) in ops {
+            for _ in 0..n {
+                if is_insert {
+                    quantity += 1;
+                    let is_full = rb_crank.is_full();
+                    if rb_crank.insert(key, key, key, quantity, 0).is_ok() == is_full {
+                        return false;
+                    }
+                    if !is_full {
+                        model.push_back(quantity);
+                    }
+                } else if rb_crank.remove_head().map(|crank| crank.get_quantity())
+                    != model.pop_front()
+                {
+                    return false;
+                }
+                if rb_crank.len() as usize != model.len() {
+                    return false;
+                }
+            }
+        }
+        true
     }
 }
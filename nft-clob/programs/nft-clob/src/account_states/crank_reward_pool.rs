@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::errors::ErrorCode;
+
+/// Crank Reward Pool
+///
+/// Collects the crank reward for every crank queued at order placement in
+/// lamports and pays it to keepers for every processed crank of the
+/// instrument group.
+#[account]
+pub struct CrankRewardPool {
+    pub instrmt_grp: Pubkey, // Instrument group the pool belongs to.
+    pub bump: u8,
+}
+
+impl CrankRewardPool {
+    pub fn space() -> usize {
+        8 + 32 + 1
+    }
+
+    /// Reward for a number of cranks.
+    pub fn reward(reward_lamports: u64, cranks: u64) -> Result<u64> {
+        reward_lamports
+            .checked_mul(cranks)
+            .ok_or_else(|| ErrorCode::CrankRewardOverflow.into())
+    }
+
+    /// Charges the reward for the cranks an order queued to its maker.
+    pub fn charge_reward<'info>(
+        pool: &AccountInfo<'info>,
+        payer: &AccountInfo<'info>,
+        system_program: &AccountInfo<'info>,
+        reward: u64,
+    ) -> Result<()> {
+        if reward == 0 {
+            return Ok(());
+        }
+        let cpi_accounts = system_program::Transfer {
+            from: payer.clone(),
+            to: pool.clone(),
+        };
+        let cpi_context = CpiContext::new(system_program.clone(), cpi_accounts);
+        system_program::transfer(cpi_context, reward)
+    }
+
+    /// Reward that can be paid without going below the rent-exempt balance.
+    pub fn payable(lamports: u64, min_balance: u64, reward: u64) -> u64 {
+        reward.min(lamports.saturating_sub(min_balance))
+    }
+
+    /// Pays a reward to a keeper, or what is left in the pool.
+    ///
+    /// The pool is owned by the program, hence lamports are moved directly.
+    pub fn pay_reward(pool: &AccountInfo, keeper: &AccountInfo, reward: u64) -> Result<u64> {
+        let min_balance = Rent::get()?.minimum_balance(pool.data_len());
+        let amount = CrankRewardPool::payable(pool.lamports(), min_balance, reward);
+
+        **pool.try_borrow_mut_lamports()? -= amount;
+        **keeper.try_borrow_mut_lamports()? += amount;
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CrankRewardPool;
+
+    #[test]
+    fn it_should_not_pay_below_rent_exempt_balance() {
+        assert_eq!(CrankRewardPool::payable(1_000, 900, 50), 50);
+        assert_eq!(CrankRewardPool::payable(1_000, 900, 500), 100);
+        assert_eq!(CrankRewardPool::payable(800, 900, 50), 0);
+    }
+
+    #[test]
+    fn it_should_reject_overflowing_rewards() {
+        assert_eq!(CrankRewardPool::reward(5_000, 3).unwrap(), 15_000);
+        assert!(CrankRewardPool::reward(u64::MAX, 2).is_err());
+    }
+}
@@ -1,27 +1,57 @@
+use std::{
+    mem::size_of,
+    ops::{Deref, DerefMut},
+};
+
 use anchor_lang::prelude::*;
 
 use crate::enums::BookSide;
-
-use super::Order;
-
-const REPORT_SIZE: u8 = 200;
+use crate::errors::ErrorCode;
 
 // Ring Buffer Filled Execution Report
+//
+// Reports are numbered from 1 and the one numbered seq_num is kept at
+// (seq_num - 1) % capacity, overwriting the report capacity numbers before.
 #[account(zero_copy)]
-pub struct RingBufferFilledExecReport {
-    pub filled_exec_reports: [FilledExecReport; REPORT_SIZE as usize],
-    pub next_index: u8,
-    pub padding: [u8; 7],
+pub struct RingBufferFilledExecReportHeader {
+    pub seq_num: u64,  // Sequence number of the last report, 0 if none.
+    pub capacity: u16, // Number of reports kept, sized from the account.
+    pub padding: [u8; 6],
+}
+
+impl RingBufferFilledExecReportHeader {
+    /// Sequence number of the oldest report still kept.
+    pub fn first_available(&self) -> u64 {
+        self.seq_num.saturating_sub(self.capacity as u64) + 1
+    }
+}
+
+/// Why a consumer could not read the reports following the last one seen.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReportReadError {
+    /// Account data is too short for its capacity.
+    InvalidData,
+    /// Reports were overwritten before they were read. Reading resumes with
+    /// first_available - 1 as the last sequence number seen.
+    Gap {
+        first_missed: u64,
+        first_available: u64,
+    },
+}
+
+/// Reports of the ring buffer over the account data.
+pub struct RingBufferFilledExecReport<'a> {
+    header: &'a mut RingBufferFilledExecReportHeader,
+    pub filled_exec_reports: &'a mut [FilledExecReport],
 }
 
 #[cfg(test)]
-impl RingBufferFilledExecReport {
-    pub fn new() -> Self {
-        Self {
-            filled_exec_reports: [FilledExecReport::new_empty(); REPORT_SIZE as usize],
-            next_index: 0,
-            padding: [0u8; 7],
-        }
+impl<'a> RingBufferFilledExecReport<'a> {
+    /// Ring buffer with a capacity of reports over a zeroed buffer.
+    pub fn new(data: &'a mut Vec<u64>, capacity: u16) -> Self {
+        data.resize(Self::space(capacity) / 8, 0);
+        let data: &mut [u8] = bytemuck::cast_slice_mut(data);
+        Self::init(data).unwrap()
     }
 }
 #[cfg(test)]
@@ -32,35 +62,153 @@ impl FilledExecReport {
             taker: Pubkey::default(),
             quantity: 0,
             price: 0,
+            maker_fee: 0,
+            taker_fee: 0,
+            maker_order_id: 0,
+            maker_client_order_id: 0,
+            taker_order_id: 0,
+            taker_client_order_id: 0,
             slot: 0,
             transact_time: 0,
+            seq_num: 0,
             side: 0,
             padding: [0u8; 7],
         }
     }
 }
 
-impl RingBufferFilledExecReport {
-    pub fn insert(&mut self, filled_exec_report: FilledExecReport) {
-        self.filled_exec_reports[self.next_index as usize] = filled_exec_report;
-        self.next_index = (self.next_index + 1) % REPORT_SIZE
+impl<'a> RingBufferFilledExecReport<'a> {
+    /// Sizes a new ring buffer from the length of the account data.
+    pub fn init(data: &'a mut [u8]) -> Result<Self> {
+        let capacity = (data.len().saturating_sub(Self::space(0)) / FilledExecReport::space())
+            .min(u16::MAX as usize) as u16;
+        require!(capacity > 0, ErrorCode::InvalidCapacity);
+        let header: &mut RingBufferFilledExecReportHeader = bytemuck::try_from_bytes_mut(
+            &mut data[8..8 + size_of::<RingBufferFilledExecReportHeader>()],
+        )
+        .map_err(|_| ErrorCode::InvalidCapacity)?;
+        header.capacity = capacity;
+        Self::load_mut(data)
     }
 
-    pub fn space() -> usize {
-        REPORT_SIZE as usize * FilledExecReport::space() + 1 + 7
+    /// Ring buffer over the account data, including the discriminator.
+    pub fn load_mut(data: &'a mut [u8]) -> Result<Self> {
+        require!(data.len() >= Self::space(0), ErrorCode::InvalidCapacity);
+        let (header, reports) =
+            data[8..].split_at_mut(size_of::<RingBufferFilledExecReportHeader>());
+        let header: &mut RingBufferFilledExecReportHeader =
+            bytemuck::try_from_bytes_mut(header).map_err(|_| ErrorCode::InvalidCapacity)?;
+        let len = header.capacity as usize * FilledExecReport::space();
+        require!(
+            header.capacity > 0 && reports.len() >= len,
+            ErrorCode::InvalidCapacity
+        );
+        Ok(Self {
+            header,
+            filled_exec_reports: bytemuck::try_cast_slice_mut(&mut reports[..len])
+                .map_err(|_| ErrorCode::InvalidCapacity)?,
+        })
+    }
+
+    /// Numbers a report and overwrites the oldest one with it.
+    pub fn insert(&mut self, mut filled_exec_report: FilledExecReport) {
+        self.header.seq_num += 1;
+        filled_exec_report.seq_num = self.header.seq_num;
+        let index = (self.header.seq_num - 1) % self.header.capacity as u64;
+        self.filled_exec_reports[index as usize] = filled_exec_report;
+    }
+
+    /// Reports numbered after last_seen, in sequence order.
+    pub fn reports_since(
+        &self,
+        last_seen: u64,
+    ) -> std::result::Result<Vec<FilledExecReport>, ReportReadError> {
+        Self::collect_since(self.header, last_seen, |index| {
+            self.filled_exec_reports[index]
+        })
+    }
+
+    /// Reports numbered after last_seen, in sequence order, read from a copy
+    /// of the account data such as fetched over RPC. The copy does not need
+    /// to be aligned.
+    pub fn read_since(
+        data: &[u8],
+        last_seen: u64,
+    ) -> std::result::Result<Vec<FilledExecReport>, ReportReadError> {
+        let header_end = 8 + size_of::<RingBufferFilledExecReportHeader>();
+        let header: RingBufferFilledExecReportHeader = bytemuck::pod_read_unaligned(
+            data.get(8..header_end)
+                .ok_or(ReportReadError::InvalidData)?,
+        );
+        if header.capacity == 0 || data.len() < Self::space(header.capacity) {
+            return Err(ReportReadError::InvalidData);
+        }
+        Self::collect_since(&header, last_seen, |index| {
+            let start = header_end + index * FilledExecReport::space();
+            bytemuck::pod_read_unaligned(&data[start..start + FilledExecReport::space()])
+        })
+    }
+
+    fn collect_since(
+        header: &RingBufferFilledExecReportHeader,
+        last_seen: u64,
+        get: impl Fn(usize) -> FilledExecReport,
+    ) -> std::result::Result<Vec<FilledExecReport>, ReportReadError> {
+        let first_available = header.first_available();
+        if last_seen + 1 < first_available {
+            return Err(ReportReadError::Gap {
+                first_missed: last_seen + 1,
+                first_available,
+            });
+        }
+        (last_seen + 1..=header.seq_num)
+            .map(|seq_num| {
+                let report = get(((seq_num - 1) % header.capacity as u64) as usize);
+                match report.seq_num == seq_num {
+                    true => Ok(report),
+                    false => Err(ReportReadError::InvalidData),
+                }
+            })
+            .collect()
+    }
+
+    pub fn space(capacity: u16) -> usize {
+        8 + size_of::<RingBufferFilledExecReportHeader>()
+            + capacity as usize * FilledExecReport::space()
+    }
+}
+
+impl Deref for RingBufferFilledExecReport<'_> {
+    type Target = RingBufferFilledExecReportHeader;
+
+    fn deref(&self) -> &Self::Target {
+        self.header
+    }
+}
+
+impl DerefMut for RingBufferFilledExecReport<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.header
     }
 }
 
 // Filled Execution Report
 #[zero_copy]
 pub struct FilledExecReport {
-    pub maker: Pubkey,      // Seller.
-    pub taker: Pubkey,      // Buyer.
-    pub quantity: u64,      // Total quantity filled.
-    pub price: u64,         // Unit price.
-    pub slot: u64,          // Slot of execution.
-    pub transact_time: i64, // Time of execution, expressed in UTC.
-    pub side: u8,           // Buy = 0 or sell = 1.
+    pub maker: Pubkey,              // Seller.
+    pub taker: Pubkey,              // Buyer.
+    pub quantity: u64,              // Total quantity filled.
+    pub price: u64,                 // Unit price.
+    pub maker_fee: u64,             // Fee charged to maker in quote.
+    pub taker_fee: u64,             // Fee charged to taker in quote.
+    pub maker_order_id: u64,        // Order id of the resting order.
+    pub maker_client_order_id: u64, // Client order id of the resting order.
+    pub taker_order_id: u64,        // Order id of the incoming order.
+    pub taker_client_order_id: u64, // Client order id of the incoming order.
+    pub slot: u64,                  // Slot of execution.
+    pub transact_time: i64,         // Time of execution, expressed in UTC.
+    pub seq_num: u64,               // Sequence number, assigned by the ring buffer.
+    pub side: u8,                   // Taker side, sell = 0 or buy = 1.
     pub padding: [u8; 7],
 }
 
@@ -79,8 +227,15 @@ impl FilledExecReport {
             taker,
             quantity,
             price,
+            maker_fee: 0,
+            taker_fee: 0,
+            maker_order_id: 0,
+            maker_client_order_id: 0,
+            taker_order_id: 0,
+            taker_client_order_id: 0,
             slot,
             transact_time,
+            seq_num: 0,
             side: if is_buy {
                 BookSide::Buy as u8
             } else {
@@ -89,7 +244,108 @@ impl FilledExecReport {
             padding: [0u8; 7],
         }
     }
+
+    pub fn with_fees(mut self, maker_fee: u64, taker_fee: u64) -> Self {
+        self.maker_fee = maker_fee;
+        self.taker_fee = taker_fee;
+        self
+    }
+
+    pub fn with_order_ids(
+        mut self,
+        maker_order_id: u64,
+        maker_client_order_id: u64,
+        taker_order_id: u64,
+        taker_client_order_id: u64,
+    ) -> Self {
+        self.maker_order_id = maker_order_id;
+        self.maker_client_order_id = maker_client_order_id;
+        self.taker_order_id = taker_order_id;
+        self.taker_client_order_id = taker_client_order_id;
+        self
+    }
+
     pub fn space() -> usize {
-        32 * 2 + 8 * 4 + 1 + 7
+        32 * 2 + 8 * 11 + 1 + 7
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::prelude::Pubkey;
+    use quickcheck_macros::quickcheck;
+
+    use super::{FilledExecReport, ReportReadError, RingBufferFilledExecReport};
+
+    fn report(quantity: u64) -> FilledExecReport {
+        let key = Pubkey::default();
+        FilledExecReport::new(key, key, quantity, 10, true, 1, 2)
+    }
+
+    #[quickcheck]
+    fn it_should_read_reports_in_order_or_signal_a_gap(
+        capacity: u8,
+        inserts: u8,
+        last_seen: u8,
+    ) -> bool {
+        let capacity = capacity as u16 % 16 + 1;
+        let (inserts, last_seen) = (inserts as u64, last_seen as u64 % (inserts as u64 + 1));
+        let mut rb_data = Vec::new();
+        let mut rb = RingBufferFilledExecReport::new(&mut rb_data, capacity);
+        for quantity in 1..=inserts {
+            rb.insert(report(quantity));
+        }
+
+        let first_available = inserts.saturating_sub(capacity as u64) + 1;
+        match rb.reports_since(last_seen) {
+            Ok(reports) => {
+                last_seen + 1 >= first_available
+                    && reports.len() as u64 == inserts - last_seen
+                    && reports
+                        .iter()
+                        .zip(last_seen + 1..)
+                        .all(|(report, seq_num)| {
+                            report.seq_num == seq_num && report.quantity == seq_num
+                        })
+            }
+            Err(gap) => {
+                gap == ReportReadError::Gap {
+                    first_missed: last_seen + 1,
+                    first_available,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_read_unaligned_copies_of_the_account() {
+        let mut rb_data = Vec::new();
+        let mut rb = RingBufferFilledExecReport::new(&mut rb_data, 3);
+        for quantity in 1..=4 {
+            rb.insert(report(quantity));
+        }
+
+        // Account data as fetched, one byte off the alignment of the reports.
+        let mut data = vec![0u8];
+        data.extend_from_slice(bytemuck::cast_slice(&rb_data));
+        let data = &data[1..];
+
+        let reports = RingBufferFilledExecReport::read_since(data, 2).unwrap();
+        let quantities: Vec<u64> = reports.iter().map(|report| report.quantity).collect();
+        assert_eq!(quantities, vec![3, 4]);
+        assert!(RingBufferFilledExecReport::read_since(data, 4)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            RingBufferFilledExecReport::read_since(data, 0).err(),
+            Some(ReportReadError::Gap {
+                first_missed: 1,
+                first_available: 2,
+            })
+        );
+        assert_eq!(
+            RingBufferFilledExecReport::read_since(&data[..data.len() - 1], 2).err(),
+            Some(ReportReadError::InvalidData)
+        );
     }
 }
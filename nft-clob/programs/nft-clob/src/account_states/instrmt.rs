@@ -1,19 +1,28 @@
 use anchor_lang::prelude::*;
 
+use crate::enums::OverflowPolicy;
+use crate::errors::ErrorCode;
+
 /// Instrument
 #[account]
 #[derive(Default)]
 pub struct Instrmt {
-    pub base_symbol: [u8; 10],          // Symbol for base, eg. BTC.
-    pub quote_symbol: [u8; 10],         // Symbol for quote, eg. USD.
-    pub instrmt_grp: Pubkey,            // Instrument group instrument belongs to.
-    pub base_mint: Pubkey,              // Base currency.
-    pub base_vault: Pubkey,             // Vault to store base currency.
-    pub quote_mint: Pubkey,             // Quote currency.
-    pub quote_vault: Pubkey,            // Vault to store quote currency.
-    pub book: Pubkey,                   // Central limit order book.
-    pub rb_filled_exec_reports: Pubkey, // Execution reports for activity view.
-    pub bumps: InstrmtBumps,            // Bumps,
+    pub base_symbol: [u8; 10],           // Symbol for base, eg. BTC.
+    pub quote_symbol: [u8; 10],          // Symbol for quote, eg. USD.
+    pub instrmt_grp: Pubkey,             // Instrument group instrument belongs to.
+    pub base_mint: Pubkey,               // Base currency.
+    pub base_vault: Pubkey,              // Vault to store base currency.
+    pub quote_mint: Pubkey,              // Quote currency.
+    pub quote_vault: Pubkey,             // Vault to store quote currency.
+    pub book: Pubkey,                    // Central limit order book.
+    pub rb_filled_exec_reports: Pubkey,  // Execution reports for activity view.
+    pub tick_size: u64,                  // Limits must be a multiple of the tick size.
+    pub lot_size: u64,                   // Sizes must be a multiple of the lot size.
+    pub min_size: u64,                   // Smallest order size.
+    pub max_size: u64,                   // Largest order size.
+    pub overflow_policy: OverflowPolicy, // Applied when a side of the book is full.
+    pub min_eviction_ticks: u64,         // Ticks to beat the worst order by to evict it.
+    pub bumps: InstrmtBumps,             // Bumps,
 }
 
 #[derive(Default, Clone, AnchorDeserialize, AnchorSerialize)]
@@ -25,7 +34,23 @@ pub struct InstrmtBumps {
 
 impl Instrmt {
     pub fn space() -> usize {
-        8  + 20 + 7 * 32 + InstrmtBumps::space()
+        8 + 20 + 7 * 32 + 8 * 4 + 1 + 8 + InstrmtBumps::space()
+    }
+
+    /// A limit of zero would be taken for an empty slot by the book.
+    pub fn validate_limit(&self, limit: u64) -> Result<()> {
+        require!(limit > 0, ErrorCode::ZeroPrice);
+        require!(limit % self.tick_size == 0, ErrorCode::InvalidTickSize);
+        Ok(())
+    }
+
+    /// A size of zero would be taken for a tombstone by the book.
+    pub fn validate_size(&self, size: u64) -> Result<()> {
+        require!(size > 0, ErrorCode::ZeroSize);
+        require!(size % self.lot_size == 0, ErrorCode::InvalidLotSize);
+        require!(size >= self.min_size, ErrorCode::OrderSizeTooSmall);
+        require!(size <= self.max_size, ErrorCode::OrderSizeTooLarge);
+        Ok(())
     }
 
     pub fn to_u8_array(a: String) -> [u8; 10] {
@@ -41,3 +66,30 @@ impl InstrmtBumps {
         1 + 1 + 1
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Instrmt;
+
+    #[test]
+    fn it_should_validate_limits_and_sizes() {
+        let instrmt = Instrmt {
+            tick_size: 5,
+            lot_size: 2,
+            min_size: 4,
+            max_size: 10,
+            ..Default::default()
+        };
+
+        assert!(instrmt.validate_limit(0).is_err());
+        assert!(instrmt.validate_limit(7).is_err());
+        assert!(instrmt.validate_limit(15).is_ok());
+
+        assert!(instrmt.validate_size(0).is_err());
+        assert!(instrmt.validate_size(5).is_err());
+        assert!(instrmt.validate_size(2).is_err());
+        assert!(instrmt.validate_size(12).is_err());
+        assert!(instrmt.validate_size(4).is_ok());
+        assert!(instrmt.validate_size(10).is_ok());
+    }
+}
@@ -9,11 +9,12 @@ use anchor_lang::prelude::*;
 pub struct InstrmtGrp {
     pub admin: Pubkey, // Owner of the instrument group.
     pub instrmts: Vec<Pubkey>,
+    pub crank_reward_lamports: u64, // Charged per queued crank and paid per processed crank.
     pub bump: u8,
 }
 
 impl InstrmtGrp {
     pub fn space(len: usize) -> usize {
-        8 + 32 + (4 + 32 * len) + 1
+        8 + 32 + (4 + 32 * len) + 8 + 1
     }
 }
@@ -15,6 +15,14 @@ pub struct MasterCfg {
 
 impl MasterCfg {
     pub fn space(len: usize) -> usize {
-        8 + 1 + (4 + 32 * len) + 32
+        8 + 1 + 1 + (4 + 32 * len) + 32 + 1
+    }
+
+    /// Maker and taker fee in BPS for a client.
+    pub fn get_fees_bps(&self, client: &Pubkey) -> (u8, u8) {
+        if self.fee_exempt.contains(client) {
+            return (0, 0);
+        }
+        (self.maker_fee_bps, self.taker_fee_bps)
     }
 }
@@ -1,21 +1,29 @@
 pub mod book;
 pub mod crank;
+pub mod crank_reward_pool;
 pub mod filled_exec_report;
 pub mod instrmt;
 pub mod instrmt_grp;
 pub mod master_cfg;
+pub mod nft_inventory;
 pub mod nft_pool;
+pub mod open_orders;
 pub mod order;
 pub mod order_header;
+pub mod price_level;
 pub mod side;
 
 pub use book::*;
 pub use crank::*;
+pub use crank_reward_pool::*;
 pub use filled_exec_report::*;
 pub use instrmt::*;
 pub use instrmt_grp::*;
 pub use master_cfg::*;
+pub use nft_inventory::*;
 pub use nft_pool::*;
+pub use open_orders::*;
 pub use order::*;
 pub use order_header::*;
+pub use price_level::*;
 pub use side::*;
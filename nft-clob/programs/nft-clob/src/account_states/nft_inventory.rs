@@ -0,0 +1,231 @@
+use anchor_lang::prelude::*;
+
+pub const MAX_NFTS: u16 = 1024;
+const NIL: u16 = u16::MAX;
+
+/// NFT Inventory
+///
+/// NFTs held by an NFT pool kept in a doubly linked list in deposit order,
+/// oldest first. Freed slots are recycled so insert and remove are O(1).
+#[account(zero_copy)]
+pub struct NftInventory {
+    pub nft_pool: Pubkey, // NFT pool the inventory belongs to.
+    pub nfts: [NftEntry; MAX_NFTS as usize],
+    pub head: u16,      // Oldest deposit.
+    pub tail: u16,      // Newest deposit.
+    pub free: u16,      // First recycled slot.
+    pub next_free: u16, // First slot never used.
+}
+
+#[cfg(test)]
+#[allow(clippy::new_without_default)]
+impl NftInventory {
+    pub fn new() -> Self {
+        let mut inventory = Self {
+            nft_pool: Pubkey::default(),
+            nfts: [NftEntry::new_empty(); MAX_NFTS as usize],
+            head: 0,
+            tail: 0,
+            free: 0,
+            next_free: 0,
+        };
+        inventory.init();
+        inventory
+    }
+}
+
+impl NftInventory {
+    pub fn init(&mut self) {
+        self.head = NIL;
+        self.tail = NIL;
+        self.free = NIL;
+        self.next_free = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head == NIL
+    }
+
+    /// Position of the oldest deposit.
+    pub fn oldest(&self) -> Option<u16> {
+        match self.head {
+            NIL => None,
+            head => Some(head),
+        }
+    }
+
+    pub fn get(&self, pos: u16) -> Option<&NftEntry> {
+        match self.nfts.get(pos as usize) {
+            Some(entry) if !entry.is_empty() => Some(entry),
+            _ => None,
+        }
+    }
+
+    /// Iterate over all NFTs in deposit order.
+    pub fn iter(&self) -> impl Iterator<Item = &NftEntry> {
+        let mut pos = self.head;
+        std::iter::from_fn(move || {
+            if pos == NIL {
+                return None;
+            }
+            let entry = &self.nfts[pos as usize];
+            pos = entry.next;
+            Some(entry)
+        })
+    }
+
+    /// Constant O(1). Returns the position of the new entry, or None if full.
+    pub fn insert(&mut self, mint: Pubkey, depositor: Pubkey, slot: u64) -> Option<u16> {
+        let pos = if self.free != NIL {
+            let pos = self.free;
+            self.free = self.nfts[pos as usize].next;
+            pos
+        } else if self.next_free < MAX_NFTS {
+            self.next_free += 1;
+            self.next_free - 1
+        } else {
+            return None;
+        };
+
+        self.nfts[pos as usize] = NftEntry {
+            mint,
+            depositor,
+            slot,
+            next: NIL,
+            prev: self.tail,
+            padding: [0; 4],
+        };
+        match self.tail {
+            NIL => self.head = pos,
+            tail => self.nfts[tail as usize].next = pos,
+        }
+        self.tail = pos;
+        Some(pos)
+    }
+
+    /// Constant O(1). Caller must make sure the position holds an NFT.
+    pub fn remove(&mut self, pos: u16) -> NftEntry {
+        let entry = self.nfts[pos as usize];
+        match entry.prev {
+            NIL => self.head = entry.next,
+            prev => self.nfts[prev as usize].next = entry.next,
+        }
+        match entry.next {
+            NIL => self.tail = entry.prev,
+            next => self.nfts[next as usize].prev = entry.prev,
+        }
+
+        self.nfts[pos as usize].clear();
+        self.nfts[pos as usize].next = self.free;
+        self.free = pos;
+        entry
+    }
+}
+
+#[zero_copy]
+#[derive(Debug)]
+pub struct NftEntry {
+    pub mint: Pubkey,      // NFT mint.
+    pub depositor: Pubkey, // Who swapped the NFT into the pool.
+    pub slot: u64,         // Slot of deposit.
+    pub next: u16,
+    pub prev: u16,
+    padding: [u8; 4],
+}
+
+#[cfg(test)]
+impl NftEntry {
+    pub fn new_empty() -> Self {
+        Self {
+            mint: Pubkey::default(),
+            depositor: Pubkey::default(),
+            slot: 0,
+            next: 0,
+            prev: 0,
+            padding: [0; 4],
+        }
+    }
+}
+
+impl NftEntry {
+    pub fn is_empty(&self) -> bool {
+        self.mint == Pubkey::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.mint = Pubkey::default();
+        self.depositor = Pubkey::default();
+        self.slot = 0;
+        self.prev = NIL;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::prelude::Pubkey;
+
+    use super::{NftInventory, MAX_NFTS};
+
+    #[test]
+    fn it_should_keep_deposit_order() {
+        let mut inventory = NftInventory::new();
+        let mints: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        for (slot, mint) in mints.iter().enumerate() {
+            inventory.insert(*mint, Pubkey::default(), slot as u64);
+        }
+
+        let held: Vec<Pubkey> = inventory.iter().map(|entry| entry.mint).collect();
+        assert_eq!(held, mints);
+        assert_eq!(
+            inventory.get(inventory.oldest().unwrap()).unwrap().mint,
+            mints[0]
+        );
+    }
+
+    #[test]
+    fn it_should_remove_and_recycle_slots() {
+        let mut inventory = NftInventory::new();
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let pos: Vec<u16> = mints
+            .iter()
+            .map(|mint| inventory.insert(*mint, Pubkey::default(), 0).unwrap())
+            .collect();
+
+        assert_eq!(inventory.remove(pos[0]).mint, mints[0]);
+        assert_eq!(inventory.remove(pos[2]).mint, mints[2]);
+        assert!(inventory.get(pos[2]).is_none());
+
+        let held: Vec<Pubkey> = inventory.iter().map(|entry| entry.mint).collect();
+        assert_eq!(held, vec![mints[1], mints[3]]);
+
+        // Most recently freed slot is reused first and appended as newest.
+        let mint = Pubkey::new_unique();
+        assert_eq!(inventory.insert(mint, Pubkey::default(), 0), Some(pos[2]));
+        assert_eq!(inventory.tail, pos[2]);
+
+        assert_eq!(inventory.remove(pos[1]).mint, mints[1]);
+        assert_eq!(inventory.remove(pos[3]).mint, mints[3]);
+        assert_eq!(inventory.remove(pos[2]).mint, mint);
+        assert!(inventory.is_empty());
+        assert_eq!(inventory.iter().count(), 0);
+    }
+
+    #[test]
+    fn it_should_reject_deposits_when_full() {
+        let mut inventory = NftInventory::new();
+        for _ in 0..MAX_NFTS {
+            assert!(inventory
+                .insert(Pubkey::new_unique(), Pubkey::default(), 0)
+                .is_some());
+        }
+        assert!(inventory
+            .insert(Pubkey::new_unique(), Pubkey::default(), 0)
+            .is_none());
+
+        inventory.remove(10);
+        assert_eq!(
+            inventory.insert(Pubkey::new_unique(), Pubkey::default(), 0),
+            Some(10)
+        );
+    }
+}
@@ -1,15 +1,97 @@
 use anchor_lang::prelude::Pubkey;
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_spl::metadata::MetadataAccount;
 
+/// NFT Pool
+///
+/// Holds NFTs matching its criteria: verified creators, a verified
+/// Metaplex collection, or both.
 #[account]
 pub struct NftPool {
+    pub authority: Pubkey, // Creator of the pool.
     pub verified_creators: Vec<Pubkey>,
-    pub mint: Pubkey,
+    pub collection: Option<Pubkey>, // Verified collection mint.
+    pub criteria_hash: [u8; 32],    // Seed identifying the pool by its criteria.
+    pub mint: Pubkey,               // Pool token, one unit per NFT held by the pool.
+    pub inventory: Pubkey,          // NFTs held by the pool.
     pub bump: u8,
+    pub mint_bump: u8,
 }
 
 impl NftPool {
     pub fn space(len: usize) -> usize {
-        8 + (4 + 32 * len) + 32 + 1
+        8 + 32 + (4 + 32 * len) + (1 + 32) + 32 + 32 + 32 + 1 + 1
+    }
+
+    /// Fixed size seed for any number of creators.
+    pub fn criteria_hash(verified_creators: &[Pubkey], collection: &Option<Pubkey>) -> [u8; 32] {
+        let mut criteria: Vec<&[u8]> = vec![b"creators"];
+        criteria.extend(verified_creators.iter().map(|creator| creator.as_ref()));
+        if let Some(collection) = collection {
+            criteria.push(b"collection");
+            criteria.push(collection.as_ref());
+        }
+        hashv(&criteria).to_bytes()
+    }
+
+    pub fn has_criteria(verified_creators: &[Pubkey], collection: &Option<Pubkey>) -> bool {
+        !verified_creators.is_empty() || collection.is_some()
+    }
+
+    /// An NFT belongs to the pool if it meets all of the pool's criteria.
+    pub fn is_member(&self, metadata: &MetadataAccount) -> bool {
+        Self::has_criteria(&self.verified_creators, &self.collection)
+            && self.is_verified_by_creators(metadata)
+            && self.is_verified_in_collection(metadata)
+    }
+
+    /// Every creator of the pool has verified the NFT.
+    fn is_verified_by_creators(&self, metadata: &MetadataAccount) -> bool {
+        if self.verified_creators.is_empty() {
+            return true;
+        }
+        let creators = match &metadata.data.creators {
+            None => return false,
+            Some(creators) => creators,
+        };
+        self.verified_creators.iter().all(|verified_creator| {
+            creators
+                .iter()
+                .any(|creator| creator.verified && creator.address == *verified_creator)
+        })
+    }
+
+    /// The NFT is a verified member of the pool's collection.
+    fn is_verified_in_collection(&self, metadata: &MetadataAccount) -> bool {
+        match (&self.collection, &metadata.collection) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(pool_collection), Some(collection)) => {
+                collection.verified && collection.key == *pool_collection
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::prelude::Pubkey;
+
+    use super::NftPool;
+
+    #[test]
+    fn it_should_hash_criteria_into_a_single_seed() {
+        let creators: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let collection = Some(Pubkey::new_unique());
+
+        let by_creators = NftPool::criteria_hash(&creators, &None);
+        let by_both = NftPool::criteria_hash(&creators, &collection);
+        let by_collection = NftPool::criteria_hash(&[], &collection);
+
+        assert_eq!(by_creators, NftPool::criteria_hash(&creators, &None));
+        assert_ne!(by_creators, by_both);
+        assert_ne!(by_both, by_collection);
+        assert_ne!(by_creators, NftPool::criteria_hash(&creators[1..], &None));
     }
 }
@@ -0,0 +1,148 @@
+use anchor_lang::prelude::*;
+
+/// Open orders of an owner on an instrument.
+///
+/// Base and quote balances of the owner. Orders placed with open orders fund
+/// their deposit from the free balance first and keep the proceeds of the
+/// taker for the owner to settle. Fills and refunds of resting orders are
+/// credited through the crank, unless the owner cancels the order. Owners
+/// withdraw their free balances with settle funds and close the account once
+/// nothing is left free or locked.
+#[account]
+#[derive(Default, Debug)]
+pub struct OpenOrders {
+    pub instrmt: Pubkey,     // Instrument the open orders belong to.
+    pub owner: Pubkey,       // Owner allowed to settle funds.
+    pub base_vault: Pubkey,  // Vault of the base credited by the crank.
+    pub quote_vault: Pubkey, // Vault of the quote credited by the crank.
+    pub base_free: u64,      // Base available for withdrawal or new orders.
+    pub base_locked: u64,    // Base deposited for resting sell orders.
+    pub quote_free: u64,     // Quote available for withdrawal or new orders.
+    pub quote_locked: u64,   // Quote deposited for resting buy orders.
+    pub bump: u8,            // Bump of the open orders of the owner.
+}
+
+impl OpenOrders {
+    /// Nothing to settle and no resting orders, every resting order locks a
+    /// deposit until its last fill or refund is credited.
+    pub fn is_empty(&self) -> bool {
+        self.base_free == 0
+            && self.base_locked == 0
+            && self.quote_free == 0
+            && self.quote_locked == 0
+    }
+
+    /// Locks the deposit of a resting order.
+    pub fn lock(&mut self, is_buy: bool, amount: u64) {
+        match is_buy {
+            true => self.quote_locked += amount,
+            false => self.base_locked += amount,
+        }
+    }
+
+    /// Releases a deposit to the free balance on cancellation.
+    pub fn unlock(&mut self, is_buy: bool, amount: u64) {
+        self.consume_locked(is_buy, amount);
+        match is_buy {
+            true => self.quote_free += amount,
+            false => self.base_free += amount,
+        }
+    }
+
+    /// Removes a deposit consumed by a fill. Saturates as a crank must never
+    /// fail and hold up the queue.
+    pub fn consume_locked(&mut self, is_buy: bool, amount: u64) {
+        match is_buy {
+            true => self.quote_locked = self.quote_locked.saturating_sub(amount),
+            false => self.base_locked = self.base_locked.saturating_sub(amount),
+        }
+    }
+
+    /// Credits the proceeds of a fill.
+    pub fn credit_proceeds(&mut self, is_buy: bool, amount: u64) {
+        match is_buy {
+            true => self.base_free += amount,
+            false => self.quote_free += amount,
+        }
+    }
+
+    /// Credits a crank paid from a vault of the instrument.
+    ///
+    /// Fills release the deposit they consumed from the other side and credit
+    /// the quantity. Refunds release nothing else and unlock the quantity.
+    pub fn credit_crank(&mut self, is_base: bool, quantity: u64, released: u64) {
+        match released {
+            0 => self.unlock(!is_base, quantity),
+            _ => {
+                self.consume_locked(is_base, released);
+                self.credit_proceeds(is_base, quantity);
+            }
+        }
+    }
+
+    /// Takes up to amount from the free balance to fund a deposit.
+    pub fn take_free(&mut self, is_buy: bool, amount: u64) -> u64 {
+        let free = match is_buy {
+            true => &mut self.quote_free,
+            false => &mut self.base_free,
+        };
+        let taken = amount.min(*free);
+        *free -= taken;
+        taken
+    }
+
+    pub fn space() -> usize {
+        8 + 32 * 4 + 8 * 4 + 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OpenOrders;
+
+    #[test]
+    fn it_should_move_balances_between_locked_and_free() {
+        let mut open_orders = OpenOrders::default();
+
+        open_orders.lock(true, 100);
+        open_orders.consume_locked(true, 60);
+        open_orders.credit_proceeds(true, 6);
+        open_orders.unlock(true, 40);
+
+        assert_eq!(open_orders.quote_locked, 0);
+        assert_eq!(open_orders.quote_free, 40);
+        assert_eq!(open_orders.base_free, 6);
+
+        assert_eq!(open_orders.take_free(true, 50), 40);
+        assert_eq!(open_orders.quote_free, 0);
+    }
+
+    #[test]
+    fn it_should_credit_cranks_of_fills_and_refunds() {
+        let mut open_orders = OpenOrders::default();
+        open_orders.lock(false, 5);
+        open_orders.lock(true, 100);
+
+        // A sell fill pays quote for the base it consumed.
+        open_orders.credit_crank(false, 30, 3);
+        assert_eq!(open_orders.base_locked, 2);
+        assert_eq!(open_orders.quote_free, 30);
+
+        // A buy fill pays base for the quote it consumed.
+        open_orders.credit_crank(true, 2, 60);
+        assert_eq!(open_orders.quote_locked, 40);
+        assert_eq!(open_orders.base_free, 2);
+
+        // Refunds unlock the quantity of the vault they are paid from.
+        open_orders.credit_crank(true, 2, 0);
+        open_orders.credit_crank(false, 40, 0);
+        assert_eq!(open_orders.base_locked, 0);
+        assert_eq!(open_orders.quote_locked, 0);
+        assert_eq!(open_orders.base_free, 4);
+        assert_eq!(open_orders.quote_free, 70);
+
+        open_orders.take_free(false, 4);
+        open_orders.take_free(true, 70);
+        assert!(open_orders.is_empty());
+    }
+}
@@ -1,30 +1,63 @@
 use anchor_lang::prelude::*;
 
+use crate::constants::FEE_BPS_DENOMINATOR;
+use crate::enums::{OrderType, SelfTradeBehavior};
+use crate::errors::ErrorCode;
+
 use super::FilledExecReport;
 
 #[zero_copy]
 #[derive(Debug)]
 pub struct Order {
-    pub limit: u64,           // Limit price per unit of quantity.
-    cum_qty: u64,             // Amount executed.
-    cum_cost: u64,            // Cost of executed amount.
-    leaves_qty: u64,          // Amount open for further execution.
-    pub maker: Pubkey,        // Order creator.
-    pub payout_acc: Pubkey,   // Token account to receive the funds from an executed trade.
+    pub limit: u64,          // Limit price per unit of quantity.
+    order_id: u64,           // Assigned by the book, unique per book.
+    client_order_id: u64,    // Assigned by the client, 0 if not set.
+    cum_qty: u64,            // Amount executed.
+    cum_cost: u64,           // Cost of executed amount.
+    leaves_qty: u64,         // Amount open for further execution.
+    pub maker: Pubkey,       // Order creator.
+    pub payout_acc: Pubkey,  // Token account to receive the funds from an executed trade.
     pub payment_acc: Pubkey, // Token account for the deposit required to place an order.
+    cum_fee: u64,            // Taker fees paid on executed amount.
+    quote_budget: u64,       // Max quote to spend on fills including fees, 0 if not set.
+    expiry: i64,             // Unix timestamp the order expires at, 0 if never.
+    maker_fee_bps: u8,       // Fee charged when resting order is executed.
+    taker_fee_bps: u8,       // Fee charged when order executes immediately.
+    uses_open_orders: u8,    // 1 if the token accounts are the open orders of the maker.
+    self_trade_behavior: u8, // Applied when matching an order of the same maker.
+    order_type: u8,          // Order type placed with, re-applied on amend.
+    padding: [u8; 3],
 }
 
 #[cfg(test)]
 impl Order {
-    pub fn new_test(limit:u64, qty: u64) -> Self {
+    pub fn new_test(limit: u64, qty: u64) -> Self {
         Order {
-            limit: limit,
+            limit,
+            order_id: 0,
+            client_order_id: 0,
             cum_qty: 0,
             cum_cost: 0,
             leaves_qty: qty,
-            maker: Pubkey::default(),
+            maker: Pubkey::new_unique(),
             payout_acc: Pubkey::default(),
-            payment_acc: Pubkey::default()
+            payment_acc: Pubkey::default(),
+            cum_fee: 0,
+            quote_budget: 0,
+            expiry: 0,
+            maker_fee_bps: 0,
+            taker_fee_bps: 0,
+            uses_open_orders: 0,
+            self_trade_behavior: SelfTradeBehavior::DecrementAndCancel as u8,
+            order_type: OrderType::GTC as u8,
+            padding: [0; 3],
+        }
+    }
+
+    pub fn new_test_of(maker: Pubkey, limit: u64, qty: u64) -> Self {
+        Order {
+            maker,
+            ..Self::new_test(limit, qty)
         }
     }
 }
@@ -34,6 +67,86 @@ impl Order {
     pub fn clear_leaves_qty(&mut self) {
         self.leaves_qty = 0;
     }
+
+    pub fn get_order_id(&self) -> u64 {
+        self.order_id
+    }
+
+    pub fn set_order_id(&mut self, order_id: u64) {
+        self.order_id = order_id;
+    }
+
+    pub fn get_client_order_id(&self) -> u64 {
+        self.client_order_id
+    }
+
+    pub fn set_client_order_id(&mut self, client_order_id: u64) {
+        self.client_order_id = client_order_id;
+    }
+
+    pub fn uses_open_orders(&self) -> bool {
+        self.uses_open_orders == 1
+    }
+
+    pub fn set_uses_open_orders(&mut self, uses_open_orders: bool) {
+        self.uses_open_orders = uses_open_orders as u8;
+    }
+
+    pub fn get_self_trade_behavior(&self) -> SelfTradeBehavior {
+        SelfTradeBehavior::from_u8(self.self_trade_behavior)
+    }
+
+    pub fn set_self_trade_behavior(&mut self, self_trade_behavior: SelfTradeBehavior) {
+        self.self_trade_behavior = self_trade_behavior as u8;
+    }
+
+    pub fn get_order_type(&self) -> OrderType {
+        OrderType::from_u8(self.order_type)
+    }
+
+    pub fn set_order_type(&mut self, order_type: OrderType) {
+        self.order_type = order_type as u8;
+    }
+
+    pub fn set_quote_budget(&mut self, quote_budget: Option<u64>) {
+        self.quote_budget = quote_budget.unwrap_or(0);
+    }
+
+    pub fn set_expiry(&mut self, expiry: Option<i64>) {
+        self.expiry = expiry.unwrap_or(0);
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry > 0 && self.expiry <= now
+    }
+
+    /// Max quantity the remaining quote budget buys at a price including the
+    /// taker fee, unlimited without a budget.
+    pub fn get_affordable_qty(&self, price: u64) -> u64 {
+        if self.quote_budget == 0 {
+            return u64::MAX;
+        }
+        // Rounding may leave cost and fee just above the budget.
+        let remaining = self
+            .quote_budget
+            .saturating_sub(self.cum_cost)
+            .saturating_sub(self.cum_fee) as u128;
+        let price = price as u128;
+        let fee_bps = self.taker_fee_bps as u128;
+        let denominator = FEE_BPS_DENOMINATOR as u128;
+        let cost = |qty: u128| {
+            let gross = qty.checked_mul(price)?;
+            gross.checked_add(gross.checked_mul(fee_bps)? / denominator)
+        };
+
+        // Lower bound as the fee is rounded down.
+        let mut qty = remaining * denominator / (price * (denominator + fee_bps));
+        while cost(qty + 1).map_or(false, |cost| cost <= remaining) {
+            qty += 1;
+        }
+        qty.min(u64::MAX as u128) as u64
+    }
+
     pub fn get_cum_cost(&self) -> u64 {
         self.cum_cost
     }
@@ -42,6 +155,56 @@ impl Order {
         self.leaves_qty.checked_mul(self.limit)
     }
 
+    pub fn get_cum_fee(&self) -> u64 {
+        self.cum_fee
+    }
+
+    pub fn set_fees(&mut self, maker_fee_bps: u8, taker_fee_bps: u8) {
+        self.maker_fee_bps = maker_fee_bps;
+        self.taker_fee_bps = taker_fee_bps;
+    }
+
+    /// Fee in quote for an amount in quote, rounded down.
+    pub fn fee(amount: u64, fee_bps: u8) -> u64 {
+        (amount as u128 * fee_bps as u128 / FEE_BPS_DENOMINATOR as u128) as u64
+    }
+
+    /// Maker fee on the leaves cost. Buyers deposit it on top of the leaves
+    /// cost as makers are paid out in base.
+    pub fn get_leaves_maker_fee(&self) -> Option<u64> {
+        Some(Order::fee(self.get_leaves_cost()?, self.maker_fee_bps))
+    }
+
+    /// Deposit held for the leaves quantity, returned on cancellation.
+    pub fn get_leaves_deposit(&self, is_buy: bool) -> Option<u64> {
+        match is_buy {
+            true => self
+                .get_leaves_cost()?
+                .checked_add(self.get_leaves_maker_fee()?),
+            false => Some(self.leaves_qty),
+        }
+    }
+
+    /// Deposit owed by and proceeds owed to the taker after matching.
+    ///
+    /// Fills execute at the resting price, so buyers deposit the cost of what
+    /// filled plus the limit cost of what rests. Price improvement never
+    /// reaches the vault and needs no refund.
+    pub fn get_settlement(&self, is_buy: bool) -> Option<(u64, u64)> {
+        match is_buy {
+            true => Some((
+                self.get_leaves_deposit(true)?
+                    .checked_add(self.cum_cost)?
+                    .checked_add(self.cum_fee)?,
+                self.cum_qty,
+            )),
+            false => Some((
+                self.cum_qty.checked_add(self.leaves_qty)?,
+                self.cum_cost - self.cum_fee,
+            )),
+        }
+    }
+
     pub fn new(
         limit: u64,
         qty: u64,
@@ -50,23 +213,72 @@ impl Order {
         payment_acc: Pubkey,
     ) -> Order {
         Order {
-            limit: limit,
+            limit,
+            order_id: 0,
+            client_order_id: 0,
             cum_qty: 0,
             cum_cost: 0,
             leaves_qty: qty,
             maker,
             payout_acc,
-            payment_acc
+            payment_acc,
+            cum_fee: 0,
+            quote_budget: 0,
+            expiry: 0,
+            maker_fee_bps: 0,
+            taker_fee_bps: 0,
+            uses_open_orders: 0,
+            self_trade_behavior: SelfTradeBehavior::DecrementAndCancel as u8,
+            order_type: OrderType::GTC as u8,
+            padding: [0; 3],
         }
     }
 
+    /// Replacement for an amended order with a new limit and leaves quantity.
+    ///
+    /// Keeps the maker, token accounts, client order id, order type, expiry
+    /// and locked fees. Expired orders cannot be amended back onto the book.
+    pub fn amend(&self, limit: u64, qty: u64, now: i64) -> Result<Order> {
+        require!(!self.is_expired(now), ErrorCode::InvalidExpiry);
+        let mut order = Order::new(limit, qty, self.maker, self.payout_acc, self.payment_acc);
+        order.client_order_id = self.client_order_id;
+        order.uses_open_orders = self.uses_open_orders;
+        order.self_trade_behavior = self.self_trade_behavior;
+        order.order_type = self.order_type;
+        order.expiry = self.expiry;
+        order.set_fees(self.maker_fee_bps, self.taker_fee_bps);
+        Ok(order)
+    }
+
+    /// Decrements the leaves quantity without a trade to prevent a self-trade.
+    pub fn decrement_leaves_qty(&mut self, qty: u64) {
+        self.leaves_qty -= qty;
+    }
+
+    /// Reduces the leaves quantity in place to keep queue priority.
+    pub fn reduce_leaves_qty(&mut self, leaves_qty: u64) {
+        assert!(leaves_qty < self.leaves_qty);
+        self.leaves_qty = leaves_qty;
+    }
+
     pub fn clear(&mut self) {
         self.limit = 0;
+        self.order_id = 0;
+        self.client_order_id = 0;
         self.cum_qty = 0;
+        self.cum_cost = 0;
         self.leaves_qty = 0;
         self.maker = Pubkey::default();
         self.payout_acc = Pubkey::default();
         self.payment_acc = Pubkey::default();
+        self.cum_fee = 0;
+        self.quote_budget = 0;
+        self.expiry = 0;
+        self.maker_fee_bps = 0;
+        self.taker_fee_bps = 0;
+        self.uses_open_orders = 0;
+        self.self_trade_behavior = SelfTradeBehavior::DecrementAndCancel as u8;
+        self.order_type = OrderType::GTC as u8;
     }
 
     pub fn get_leaves_qty(&self) -> u64 {
@@ -85,55 +297,66 @@ impl Order {
         self.leaves_qty == 0
     }
 
-    pub fn is_better_than(&self, other: &Order, is_buy: bool) -> bool {
-        if other.limit == 0 {
-            return true;
-        }
-        if is_buy {
-            self.limit > other.limit
-        } else {
-            self.limit < other.limit
-        }
-    }
-
     /// Existing order matches with a new incoming order.
+    ///
+    /// Fees are charged in quote. The maker fee is the reduction of the maker
+    /// fee on the leaves cost, so partial fills add up to the fee on the
+    /// whole order.
     pub fn execute_trade(
         &mut self,
         new_order: &mut Order,
         is_buy: bool,
+        clock: &Clock,
     ) -> Result<FilledExecReport> {
-        let match_qty;
-        if new_order.leaves_qty >= self.leaves_qty {
-            match_qty = self.leaves_qty;
+        let match_qty = if new_order.leaves_qty >= self.leaves_qty {
+            self.leaves_qty
         } else {
-            match_qty = new_order.leaves_qty;
+            new_order.leaves_qty
         }
+        .min(new_order.get_affordable_qty(self.limit));
+        let match_cost = match_qty
+            .checked_mul(self.limit)
+            .ok_or(ErrorCode::FillOverflow)?;
 
-        self.cum_qty += match_qty;
+        let leaves_maker_fee = self.get_leaves_maker_fee().ok_or(ErrorCode::FillOverflow)?;
+        self.cum_qty = self
+            .cum_qty
+            .checked_add(match_qty)
+            .ok_or(ErrorCode::FillOverflow)?;
         self.leaves_qty -= match_qty;
+        // The leaves cost only shrinks, so its fee is in range.
+        let maker_fee = leaves_maker_fee - self.get_leaves_maker_fee().unwrap();
+        let taker_fee = Order::fee(match_cost, new_order.taker_fee_bps);
 
         new_order.leaves_qty -= match_qty;
-        new_order.cum_qty += match_qty;
-        new_order.cum_cost += match_qty.checked_mul(self.limit).unwrap();
-
-        let slot;
-        let transact_time;
+        new_order.cum_qty = new_order
+            .cum_qty
+            .checked_add(match_qty)
+            .ok_or(ErrorCode::FillOverflow)?;
+        new_order.cum_cost = new_order
+            .cum_cost
+            .checked_add(match_cost)
+            .ok_or(ErrorCode::FillOverflow)?;
+        new_order.cum_fee = new_order
+            .cum_fee
+            .checked_add(taker_fee)
+            .ok_or(ErrorCode::FillOverflow)?;
 
-        if cfg!(test) {
-            slot = 1;
-            transact_time = 2;
-        } else {
-            slot = Clock::get()?.slot;
-            transact_time = Clock::get()?.unix_timestamp;
-        }
         Ok(FilledExecReport::new(
             self.maker,
             new_order.maker,
             match_qty,
             self.limit,
             is_buy,
-            slot,
-            transact_time,
+            clock.slot,
+            clock.unix_timestamp,
+        )
+        .with_fees(maker_fee, taker_fee)
+        .with_order_ids(
+            self.order_id,
+            self.client_order_id,
+            new_order.order_id,
+            new_order.client_order_id,
         ))
     }
 
@@ -142,6 +365,35 @@ impl Order {
     }
 
     pub fn space() -> usize {
-        8 + (8 * 4) + 32
+        8 * 6 + 32 * 3 + 8 * 3 + 1 + 1 + 1 + 1 + 1 + 3
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Order;
+
+    #[test]
+    fn it_should_afford_nothing_past_the_quote_budget() {
+        let mut order = Order::new_test(u64::MAX, 10);
+        order.set_fees(0, 100);
+        order.set_quote_budget(Some(1_000));
+        assert_eq!(order.get_affordable_qty(99), 10);
+        assert_eq!(order.get_affordable_qty(u64::MAX), 0);
+
+        // Cost and fee rounded above the budget.
+        order.cum_cost = 990;
+        order.cum_fee = 11;
+        assert_eq!(order.get_affordable_qty(1), 0);
+    }
+
+    #[test]
+    fn it_should_not_amend_expired_orders() {
+        let mut order = Order::new_test(10, 2);
+        order.set_expiry(Some(5));
+
+        let amended = order.amend(11, 1, 4).unwrap();
+        assert_eq!((amended.limit, amended.expiry), (11, 5));
+        assert!(order.amend(11, 1, 5).is_err());
     }
 }
@@ -8,7 +8,14 @@ pub struct OrderHeader {
     pub order: Order,
     pub next: u16,
     pub prev: u16,
-    padding: [u8; 4],
+    pub level: u16, // Price level of the order.
+    padding: [u8; 2],
+}
+
+impl Default for OrderHeader {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl OrderHeader {
@@ -17,7 +24,8 @@ impl OrderHeader {
             order: Order::new(0, 0, Pubkey::default(),Pubkey::default(), Pubkey::default()),
             next: 0,
             prev: 0,
-            padding: [0; 4],
+            level: 0,
+            padding: [0; 2],
         }
     }
 }
\ No newline at end of file
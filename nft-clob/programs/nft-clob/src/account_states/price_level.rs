@@ -0,0 +1,412 @@
+use std::mem::size_of;
+
+use anchor_lang::{prelude::*, zero_copy};
+
+use crate::errors::ErrorCode;
+
+pub const NIL_LEVEL: u16 = 0; // Slot 0 is never allocated.
+
+/// Orders resting at one price.
+///
+/// The orders of a level are consecutive in the linked list of the side,
+/// oldest first, which makes every level a FIFO queue.
+#[zero_copy]
+#[derive(Debug)]
+pub struct PriceLevel {
+    pub price: u64,       // Limit of all orders at the level.
+    pub total_qty: u64,   // Sum of leaves quantities at the level.
+    pub first: u16,       // Oldest order at the level.
+    pub last: u16,        // Newest order at the level.
+    pub order_count: u16, // Number of orders at the level.
+    left: u16,            // Level with a lower price, next free level if freed.
+    right: u16,           // Level with a higher price.
+    parent: u16,          // Parent level, NIL_LEVEL for the root.
+    height: u16,          // Height of the subtree of the level, 1 for a leaf.
+    padding: [u8; 2],
+}
+
+#[zero_copy]
+pub struct PriceLevelsHeader {
+    pub root: u16, // Root level, NIL_LEVEL if empty.
+    free: u16,     // Head of the freed levels.
+    ladder: u16,   // Number of levels ever allocated.
+    padding: [u8; 2],
+}
+
+/// Price levels of a side indexed by price.
+///
+/// A slab-backed AVL tree: levels are kept in price order and the subtrees
+/// of every level differ in height by at most one, which keeps lookups,
+/// insertions and removals logarithmic in the number of levels whatever
+/// prices arrive, without any recursion.
+pub struct PriceLevels<'a> {
+    pub header: &'a mut PriceLevelsHeader,
+    pub levels: &'a mut [PriceLevel], // Slot 0 is the nil level.
+}
+
+impl<'a> PriceLevels<'a> {
+    /// Levels for a capacity of orders over a zero-copy buffer.
+    pub fn from_bytes(data: &'a mut [u8], capacity: u16) -> Result<Self> {
+        let (header, levels) = data.split_at_mut(size_of::<PriceLevelsHeader>());
+        Ok(Self {
+            header: bytemuck::try_from_bytes_mut(header).map_err(|_| ErrorCode::InvalidCapacity)?,
+            levels: bytemuck::try_cast_slice_mut(
+                &mut levels[..(capacity as usize + 1) * size_of::<PriceLevel>()],
+            )
+            .map_err(|_| ErrorCode::InvalidCapacity)?,
+        })
+    }
+
+    pub fn space(capacity: u16) -> usize {
+        size_of::<PriceLevelsHeader>() + (capacity as usize + 1) * size_of::<PriceLevel>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.root == NIL_LEVEL
+    }
+
+    pub fn get(&self, level: u16) -> &PriceLevel {
+        &self.levels[level as usize]
+    }
+
+    pub fn get_mut(&mut self, level: u16) -> &mut PriceLevel {
+        &mut self.levels[level as usize]
+    }
+
+    /// Level at a price.
+    pub fn find(&self, price: u64) -> Option<u16> {
+        let mut level = self.header.root;
+        while level != NIL_LEVEL {
+            let node = &self.levels[level as usize];
+            level = match price.cmp(&node.price) {
+                std::cmp::Ordering::Equal => return Some(level),
+                std::cmp::Ordering::Less => node.left,
+                std::cmp::Ordering::Greater => node.right,
+            };
+        }
+        None
+    }
+
+    /// Closest level behind a price, i.e. the next lower bid or the next
+    /// higher ask.
+    pub fn next_worse(&self, price: u64, is_buy: bool) -> Option<u16> {
+        let mut found = None;
+        let mut level = self.header.root;
+        while level != NIL_LEVEL {
+            let node = &self.levels[level as usize];
+            let is_worse = match is_buy {
+                true => node.price < price,
+                false => node.price > price,
+            };
+            if is_worse {
+                found = Some(level);
+            }
+            // Move towards the price while worse, away from it otherwise.
+            level = match (is_buy, is_worse) {
+                (true, true) | (false, false) => node.right,
+                (true, false) | (false, true) => node.left,
+            };
+        }
+        found
+    }
+
+    /// Adds a level holding a single order.
+    pub fn insert(&mut self, price: u64, order_pos: u16, qty: u64) -> u16 {
+        let level = self.alloc();
+        self.levels[level as usize] = PriceLevel {
+            price,
+            total_qty: qty,
+            first: order_pos,
+            last: order_pos,
+            order_count: 1,
+            left: NIL_LEVEL,
+            right: NIL_LEVEL,
+            parent: NIL_LEVEL,
+            height: 1,
+            padding: [0; 2],
+        };
+
+        // Insert as a leaf.
+        let mut parent = NIL_LEVEL;
+        let mut child = self.header.root;
+        while child != NIL_LEVEL {
+            parent = child;
+            let node = &self.levels[child as usize];
+            assert!(node.price != price, "Price level exists");
+            child = match price < node.price {
+                true => node.left,
+                false => node.right,
+            };
+        }
+        self.levels[level as usize].parent = parent;
+        match parent {
+            NIL_LEVEL => self.header.root = level,
+            _ if price < self.levels[parent as usize].price => {
+                self.levels[parent as usize].left = level
+            }
+            _ => self.levels[parent as usize].right = level,
+        }
+
+        self.rebalance_up(parent);
+        level
+    }
+
+    /// Removes an empty level.
+    ///
+    /// Levels are relinked rather than moved, as orders refer to their level
+    /// by slot.
+    pub fn remove(&mut self, level: u16) {
+        let node = self.levels[level as usize];
+        let unbalanced = if node.left != NIL_LEVEL && node.right != NIL_LEVEL {
+            // Successor takes the place of the level.
+            let mut successor = node.right;
+            while self.levels[successor as usize].left != NIL_LEVEL {
+                successor = self.levels[successor as usize].left;
+            }
+            let unbalanced = match self.levels[successor as usize].parent {
+                parent if parent == level => successor,
+                parent => {
+                    let moved = self.levels[successor as usize].right;
+                    self.levels[parent as usize].left = moved;
+                    if moved != NIL_LEVEL {
+                        self.levels[moved as usize].parent = parent;
+                    }
+                    self.levels[successor as usize].right = node.right;
+                    self.levels[node.right as usize].parent = successor;
+                    parent
+                }
+            };
+            self.levels[successor as usize].left = node.left;
+            self.levels[node.left as usize].parent = successor;
+            self.levels[successor as usize].parent = node.parent;
+            self.levels[successor as usize].height = node.height;
+            self.replace_child(node.parent, level, successor);
+            unbalanced
+        } else {
+            let child = match node.left {
+                NIL_LEVEL => node.right,
+                left => left,
+            };
+            if child != NIL_LEVEL {
+                self.levels[child as usize].parent = node.parent;
+            }
+            self.replace_child(node.parent, level, child);
+            node.parent
+        };
+
+        self.release(level);
+        self.rebalance_up(unbalanced);
+    }
+
+    fn height(&self, level: u16) -> i32 {
+        match level {
+            NIL_LEVEL => 0,
+            _ => self.levels[level as usize].height as i32,
+        }
+    }
+
+    /// Height of the left minus the height of the right subtree.
+    fn balance(&self, level: u16) -> i32 {
+        let node = &self.levels[level as usize];
+        self.height(node.left) - self.height(node.right)
+    }
+
+    fn update_height(&mut self, level: u16) {
+        let node = &self.levels[level as usize];
+        let height = self.height(node.left).max(self.height(node.right)) + 1;
+        self.levels[level as usize].height = height as u16;
+    }
+
+    /// Restores the heights and balance of a level and its ancestors.
+    fn rebalance_up(&mut self, mut level: u16) {
+        while level != NIL_LEVEL {
+            let top = self.rebalance(level);
+            level = self.levels[top as usize].parent;
+        }
+    }
+
+    /// Rotates a level out of balance back into balance and returns the level
+    /// taking its place.
+    fn rebalance(&mut self, level: u16) -> u16 {
+        self.update_height(level);
+        let balance = self.balance(level);
+        if balance.abs() <= 1 {
+            return level;
+        }
+
+        // A child leaning the other way is rotated first.
+        let child = match balance > 0 {
+            true => self.levels[level as usize].left,
+            false => self.levels[level as usize].right,
+        };
+        let child_balance = self.balance(child);
+        let top = if balance > 0 && child_balance < 0 {
+            self.levels[child as usize].right
+        } else if balance < 0 && child_balance > 0 {
+            self.levels[child as usize].left
+        } else {
+            child
+        };
+        if top != child {
+            self.rotate_up(top);
+            self.update_height(child);
+        }
+        self.rotate_up(top);
+        self.update_height(level);
+        self.update_height(top);
+        top
+    }
+
+    /// Rotates a level above its parent.
+    fn rotate_up(&mut self, level: u16) {
+        let parent = self.levels[level as usize].parent;
+        let grandparent = self.levels[parent as usize].parent;
+
+        if self.levels[parent as usize].left == level {
+            let moved = self.levels[level as usize].right;
+            self.levels[parent as usize].left = moved;
+            self.levels[level as usize].right = parent;
+            if moved != NIL_LEVEL {
+                self.levels[moved as usize].parent = parent;
+            }
+        } else {
+            let moved = self.levels[level as usize].left;
+            self.levels[parent as usize].right = moved;
+            self.levels[level as usize].left = parent;
+            if moved != NIL_LEVEL {
+                self.levels[moved as usize].parent = parent;
+            }
+        }
+        self.levels[parent as usize].parent = level;
+        self.levels[level as usize].parent = grandparent;
+        self.replace_child(grandparent, parent, level);
+    }
+
+    fn replace_child(&mut self, parent: u16, old: u16, new: u16) {
+        if parent == NIL_LEVEL {
+            self.header.root = new;
+        } else if self.levels[parent as usize].left == old {
+            self.levels[parent as usize].left = new;
+        } else {
+            self.levels[parent as usize].right = new;
+        }
+    }
+
+    fn alloc(&mut self) -> u16 {
+        if self.header.free != NIL_LEVEL {
+            let level = self.header.free;
+            self.header.free = self.levels[level as usize].left;
+            return level;
+        }
+        assert!(
+            (self.header.ladder as usize) < self.levels.len() - 1,
+            "No price level available"
+        );
+        self.header.ladder += 1;
+        self.header.ladder
+    }
+
+    fn release(&mut self, level: u16) {
+        self.levels[level as usize] = PriceLevel {
+            price: 0,
+            total_qty: 0,
+            first: 0,
+            last: 0,
+            order_count: 0,
+            left: self.header.free,
+            right: NIL_LEVEL,
+            parent: NIL_LEVEL,
+            height: 0,
+            padding: [0; 2],
+        };
+        self.header.free = level;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use quickcheck_macros::quickcheck;
+
+    use super::{PriceLevels, NIL_LEVEL};
+
+    const CAPACITY: u16 = 256;
+
+    /// Returns the size of the subtree after checking order, balance and
+    /// parent links.
+    fn check(levels: &PriceLevels, level: u16, parent: u16, lo: u64, hi: u64) -> usize {
+        if level == NIL_LEVEL {
+            return 0;
+        }
+        let node = levels.get(level);
+        assert_eq!(node.parent, parent);
+        assert!(lo <= node.price && node.price <= hi);
+        let (left, right) = (levels.height(node.left), levels.height(node.right));
+        assert_eq!(node.height as i32, left.max(right) + 1);
+        assert!((left - right).abs() <= 1);
+        1 + check(levels, node.left, level, lo, node.price.saturating_sub(1))
+            + check(levels, node.right, level, node.price + 1, hi)
+    }
+
+    #[quickcheck]
+    fn it_should_index_levels_like_an_ordered_set(ops: Vec<(bool, u8)>) -> bool {
+        let mut data = vec![0u64; PriceLevels::space(CAPACITY) / 8];
+        let mut levels =
+            PriceLevels::from_bytes(bytemuck::cast_slice_mut(&mut data), CAPACITY).unwrap();
+        let mut model = BTreeSet::new();
+
+        for (is_insert, price) in ops {
+            let price = price as u64 + 1;
+            match (is_insert, levels.find(price)) {
+                (true, None) => {
+                    levels.insert(price, 0, 1);
+                    model.insert(price);
+                }
+                (false, Some(level)) => {
+                    levels.remove(level);
+                    model.remove(&price);
+                }
+                _ => {}
+            }
+            assert_eq!(
+                check(&levels, levels.header.root, NIL_LEVEL, 0, u64::MAX),
+                model.len()
+            );
+
+            let next_lower_bid = levels.next_worse(price, true).map(|l| levels.get(l).price);
+            let next_higher_ask = levels.next_worse(price, false).map(|l| levels.get(l).price);
+            assert_eq!(next_lower_bid, model.range(..price).next_back().copied());
+            assert_eq!(next_higher_ask, model.range(price + 1..).next().copied());
+        }
+        model.iter().all(|price| levels.find(*price).is_some())
+    }
+
+    #[test]
+    fn it_should_stay_balanced_for_sorted_prices() {
+        let mut data = vec![0u64; PriceLevels::space(CAPACITY) / 8];
+        let mut levels =
+            PriceLevels::from_bytes(bytemuck::cast_slice_mut(&mut data), CAPACITY).unwrap();
+
+        // Rising prices turn an unbalanced tree into a chain.
+        for price in 1..=CAPACITY as u64 {
+            levels.insert(price, 0, 1);
+        }
+        let root = levels.header.root;
+        assert_eq!(
+            check(&levels, root, NIL_LEVEL, 0, u64::MAX),
+            CAPACITY as usize
+        );
+        assert_eq!(levels.height(root), 9);
+
+        for price in 1..=CAPACITY as u64 / 2 {
+            levels.remove(levels.find(price).unwrap());
+        }
+        let root = levels.header.root;
+        assert_eq!(
+            check(&levels, root, NIL_LEVEL, 0, u64::MAX),
+            CAPACITY as usize / 2
+        );
+        assert!(levels.height(root) <= 9);
+    }
+}
@@ -1,62 +1,82 @@
-use anchor_lang::{prelude::Pubkey, zero_copy};
+use std::{
+    mem::size_of,
+    ops::{Deref, DerefMut, RangeInclusive},
+};
 
-use super::{Order, OrderHeader, RingBufferCrank, MAX_ORDERS};
+use anchor_lang::{prelude::*, zero_copy};
+
+use crate::enums::{CancelReason, SelfTradeBehavior};
+use crate::errors::ErrorCode;
+use crate::events::OrderCancelled;
+
+use super::{OpenOrders, Order, OrderHeader, PriceLevels, RingBufferCrank, NIL_LEVEL};
 
 #[zero_copy]
-pub struct Side {
-    pub orders: [OrderHeader; MAX_ORDERS as usize],
+pub struct SideHeader {
     pub vault: Pubkey, // Vault for order deposits
     tombstone: u16,
     pub head: u16,
     pub tail: u16,
-    padding: [u8; 10],
+    padding: [u8; 2],
 }
 
-#[cfg(test)]
-impl Side {
-    pub fn new() -> Self {
-        Self {
-            orders: [OrderHeader::new(); MAX_ORDERS as usize],
-            vault: Pubkey::default(),
-            tombstone: 0,
-            head: 0,
-            tail: 0,
-            padding: [0; 10],
-        }
+/// Orders of one side of the book.
+///
+/// Orders are linked from best to worst price and oldest to newest within a
+/// price. The price levels index the list, so new orders find their place in
+/// logarithmic time.
+pub struct Side<'a> {
+    header: &'a mut SideHeader,
+    pub orders: &'a mut [OrderHeader], // Order slots, as many as the capacity.
+    pub levels: PriceLevels<'a>,       // Price levels of the orders.
+    pub instrmt: Pubkey,               // Instrument of the book, carried in events.
+}
+
+impl<'a> Side<'a> {
+    /// Side with a capacity of orders over a zero-copy buffer.
+    pub fn from_bytes(data: &'a mut [u8], capacity: u16, instrmt: Pubkey) -> Result<Self> {
+        let (header, data) = data.split_at_mut(size_of::<SideHeader>());
+        let (orders, levels) = data.split_at_mut(capacity as usize * size_of::<OrderHeader>());
+        Ok(Self {
+            header: bytemuck::try_from_bytes_mut(header).map_err(|_| ErrorCode::InvalidCapacity)?,
+            orders: bytemuck::try_cast_slice_mut(orders).map_err(|_| ErrorCode::InvalidCapacity)?,
+            levels: PriceLevels::from_bytes(levels, capacity)?,
+            instrmt,
+        })
+    }
+
+    pub fn space(capacity: u16) -> usize {
+        size_of::<SideHeader>()
+            + capacity as usize * size_of::<OrderHeader>()
+            + PriceLevels::space(capacity)
+    }
+
+    pub fn capacity(&self) -> u16 {
+        self.orders.len() as u16
     }
 }
 
-impl Side {
-    pub fn get_tombstone(&self) -> u16 {
-        self.tombstone
+impl Deref for Side<'_> {
+    type Target = SideHeader;
+
+    fn deref(&self) -> &Self::Target {
+        self.header
     }
+}
 
-    pub fn best_offer(&self) -> u64 {
-        self.orders[self.head as usize].order.limit
+impl DerefMut for Side<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.header
     }
+}
 
-    pub fn is_better_than_best(&self, new_order: &Order, is_buy: bool) -> bool {
-        let price = self.orders[self.head as usize].order.limit;
-        if price == 0 {
-            return true;
-        }
-        if is_buy {
-            new_order.limit > price
-        } else {
-            new_order.limit < price
-        }
+impl Side<'_> {
+    pub fn get_tombstone(&self) -> u16 {
+        self.header.tombstone
     }
 
-    pub fn is_worse_than_worst(&self, new_order: Order, is_buy: bool) -> bool {
-        let limit = self.orders[self.tail as usize].order.limit;
-        if limit == 0 {
-            return false;
-        }
-        if is_buy {
-            limit >= new_order.limit
-        } else {
-            limit <= new_order.limit
-        }
+    pub fn best_offer(&self) -> u64 {
+        self.orders[self.header.head as usize].order.limit
     }
 
     pub fn is_tombstone(&self, pos: u16) -> bool {
@@ -64,19 +84,19 @@ impl Side {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.orders[self.head as usize].order.is_tombstone()
+        self.orders[self.header.head as usize].order.is_tombstone()
     }
 
     pub fn is_head(&mut self, pos: u16) -> bool {
-        self.head == pos
+        self.header.head == pos
     }
 
     pub fn is_tail(&mut self, pos: u16) -> bool {
-        self.tail == pos
+        self.header.tail == pos
     }
 
     /// Gets the next order index if exists.
-    pub fn next_order(&mut self, pos: u16) -> Option<u16> {
+    pub fn next_order(&self, pos: u16) -> Option<u16> {
         let next = self.orders[pos as usize].next;
         if self.orders[next as usize].order.is_tombstone() || pos == next {
             return None;
@@ -86,47 +106,50 @@ impl Side {
 
     pub fn next_tombstone(&mut self) -> Option<u16> {
         // No more tombstones available
-        if self.tombstone == MAX_ORDERS {
+        if self.header.tombstone == self.capacity() {
             return None;
         }
-        let ret = self.tombstone;
-        let tombstone = self.orders[self.tombstone as usize];
+        let ret = self.header.tombstone;
+        let tombstone = self.orders[self.header.tombstone as usize];
 
         // We can't assign next tombstone to what we return.
         if ret == tombstone.next {
-            self.tombstone += 1;
+            self.header.tombstone += 1;
         }
-        // A tombstone can only be set to an actual tombstone.
-        else if self.orders[tombstone.next as usize].order.is_tombstone() {
-            self.tombstone = tombstone.next;
+        // A tombstone can only be set to an actual tombstone, or to the
+        // capacity if it was freed while the side was full.
+        else if tombstone.next == self.capacity()
+            || self.orders[tombstone.next as usize].order.is_tombstone()
+        {
+            self.header.tombstone = tombstone.next;
         }
         // We need to climb the ladder.
         else {
-            self.tombstone += 1;
+            self.header.tombstone += 1;
         }
 
         assert!(self.orders[ret as usize].order.is_tombstone());
 
-        return Some(ret);
+        Some(ret)
     }
 
     pub fn new_tombstone(&mut self, pos: u16) {
-        self.orders[pos as usize].next = self.tombstone;
-        self.tombstone = pos;
+        self.orders[pos as usize].next = self.header.tombstone;
+        self.header.tombstone = pos;
     }
 
     pub fn new_head(&mut self, pos: u16) {
         self.orders[pos as usize].prev = pos;
-        self.orders[pos as usize].next = self.head;
-        self.orders[self.head as usize].prev = pos;
-        self.head = pos;
+        self.orders[pos as usize].next = self.header.head;
+        self.orders[self.header.head as usize].prev = pos;
+        self.header.head = pos;
     }
 
     pub fn new_tail(&mut self, pos: u16) {
         self.orders[pos as usize].next = pos;
-        self.orders[pos as usize].prev = self.tail;
-        self.orders[self.tail as usize].next = pos;
-        self.tail = pos;
+        self.orders[pos as usize].prev = self.header.tail;
+        self.orders[self.header.tail as usize].next = pos;
+        self.header.tail = pos;
     }
 
     pub fn new_before(&mut self, new: u16, before: u16) {
@@ -137,17 +160,66 @@ impl Side {
         self.orders[before as usize].prev = new;
     }
 
-    // Constant O(1)
+    /// Inserts an order after another.
+    pub fn new_after(&mut self, new: u16, after: u16) {
+        if self.is_tail(after) {
+            self.new_tail(new);
+        } else {
+            let next = self.orders[after as usize].next;
+            self.new_before(new, next);
+        }
+    }
+
+    /// Takes the quantity filled off the level of a resting order.
+    pub fn reduce_level_qty(&mut self, ord_pos: u16, qty: u64) {
+        let level = self.orders[ord_pos as usize].level;
+        self.levels.get_mut(level).total_qty -= qty;
+    }
+
+    /// Aggregated quantity per price from best to worst, at most max_levels.
+    pub fn depth(&self, max_levels: usize) -> Vec<(u64, u64)> {
+        let mut depth = Vec::new();
+        if self.is_empty() {
+            return depth;
+        }
+        let mut pos = self.header.head;
+        while depth.len() < max_levels {
+            let level = self.levels.get(self.orders[pos as usize].level);
+            depth.push((level.price, level.total_qty));
+            pos = match self.next_order(level.last) {
+                None => break,
+                Some(next_pos) => next_pos,
+            };
+        }
+        depth
+    }
+
+    // Constant O(1) unless the level empties, O(log n) then.
     pub fn remove_order(&mut self, ord_pos: u16) -> Order {
         let order = self.orders[ord_pos as usize].order;
+
+        let level_pos = self.orders[ord_pos as usize].level;
+        assert!(level_pos != NIL_LEVEL, "Order is not on the book");
+        let level = self.levels.get_mut(level_pos);
+        level.total_qty -= order.get_leaves_qty();
+        level.order_count -= 1;
+        if level.order_count == 0 {
+            self.levels.remove(level_pos);
+        } else if level.first == ord_pos {
+            level.first = self.orders[ord_pos as usize].next;
+        } else if level.last == ord_pos {
+            level.last = self.orders[ord_pos as usize].prev;
+        }
+        self.orders[ord_pos as usize].level = NIL_LEVEL;
+
         if self.is_head(ord_pos) {
             let next = self.orders[ord_pos as usize].next;
             self.orders[next as usize].prev = next;
-            self.head = next;
+            self.header.head = next;
         } else if self.is_tail(ord_pos) {
             let prev = self.orders[ord_pos as usize].prev;
             self.orders[prev as usize].next = prev;
-            self.tail = prev;
+            self.header.tail = prev;
         } else {
             let prev = self.orders[ord_pos as usize].prev;
             let next = self.orders[ord_pos as usize].next;
@@ -162,69 +234,333 @@ impl Side {
         order
     }
 
-    pub fn insert_order(&mut self, new_order: Order, is_buy: bool, rb_crank: &mut RingBufferCrank) {
-        // Insert in constant time O(1)
-        if new_order.is_partially_filed() || self.is_better_than_best(&new_order, is_buy) {
-            let new_order_pos = match self.next_tombstone() {
-                None => {
-                    // Cancel tail
-                    let tail = self.tail;
-                    let removed_order = self.remove_order(self.tail);
-                    rb_crank.insert(
-                        self.vault,
-                        removed_order.payment_acc,
+    /// Refunds part of a deposit through the crank, or directly to the open
+    /// orders of the maker if they are at hand.
+    pub fn refund(
+        &self,
+        order: &Order,
+        amount: u64,
+        is_buy: bool,
+        rb_crank: &mut RingBufferCrank,
+        open_orders: Option<&mut OpenOrders>,
+    ) -> Result<()> {
+        match open_orders {
+            Some(open_orders) if order.uses_open_orders() && open_orders.owner == order.maker => {
+                open_orders.unlock(is_buy, amount);
+                Ok(())
+            }
+            _ => rb_crank.insert(self.header.vault, order.payment_acc, order.maker, amount, 0),
+        }
+    }
+
+    /// Removes an order and refunds its leaves deposit.
+    pub fn cancel_order(
+        &mut self,
+        ord_pos: u16,
+        is_buy: bool,
+        reason: CancelReason,
+        rb_crank: &mut RingBufferCrank,
+        open_orders: Option<&mut OpenOrders>,
+        clock: &Clock,
+    ) -> Result<Order> {
+        let removed_order = self.remove_order(ord_pos);
+        let leaves_deposit = removed_order.get_leaves_deposit(is_buy).unwrap();
+        self.refund(
+            &removed_order,
+            leaves_deposit,
+            is_buy,
+            rb_crank,
+            open_orders,
+        )?;
+        emit!(OrderCancelled::new(
+            self.instrmt,
+            &removed_order,
+            is_buy,
+            reason,
+            clock.slot
+        ));
+        Ok(removed_order)
+    }
+
+    /// Reduces the leaves quantity of an order and refunds the difference in
+    /// deposit.
+    pub fn reduce_order(
+        &mut self,
+        ord_pos: u16,
+        leaves_qty: u64,
+        is_buy: bool,
+        rb_crank: &mut RingBufferCrank,
+        open_orders: Option<&mut OpenOrders>,
+    ) -> Result<()> {
+        let reduced_qty = self.orders[ord_pos as usize].order.get_leaves_qty() - leaves_qty;
+        self.reduce_level_qty(ord_pos, reduced_qty);
+
+        let order = &mut self.orders[ord_pos as usize].order;
+        let leaves_deposit = order.get_leaves_deposit(is_buy).unwrap();
+        order.reduce_leaves_qty(leaves_qty);
+        let refund = leaves_deposit - order.get_leaves_deposit(is_buy).unwrap();
+
+        let order = self.orders[ord_pos as usize].order;
+        self.refund(&order, refund, is_buy, rb_crank, open_orders)
+    }
+
+    /// Applies the self-trade behavior of an incoming order to a resting order
+    /// of the same maker instead of matching them.
+    pub fn prevent_self_trade(
+        &mut self,
+        ord_pos: u16,
+        new_order: &mut Order,
+        is_buy: bool,
+        rb_crank: &mut RingBufferCrank,
+        open_orders: Option<&mut OpenOrders>,
+        clock: &Clock,
+    ) -> Result<()> {
+        match new_order.get_self_trade_behavior() {
+            SelfTradeBehavior::AbortTransaction => err!(ErrorCode::SelfTrade),
+            SelfTradeBehavior::CancelProvide => {
+                self.cancel_order(
+                    ord_pos,
+                    is_buy,
+                    CancelReason::SelfTrade,
+                    rb_crank,
+                    open_orders,
+                    clock,
+                )?;
+                Ok(())
+            }
+            SelfTradeBehavior::DecrementAndCancel => {
+                let leaves_qty = self.orders[ord_pos as usize].order.get_leaves_qty();
+                let qty = leaves_qty.min(new_order.get_leaves_qty());
+                new_order.decrement_leaves_qty(qty);
+                if qty == leaves_qty {
+                    self.cancel_order(
+                        ord_pos,
                         is_buy,
-                        removed_order.maker,
-                        removed_order.get_leaves_qty(),
-                        removed_order.limit,
-                    );
-                    tail
+                        CancelReason::SelfTrade,
+                        rb_crank,
+                        open_orders,
+                        clock,
+                    )?;
+                    Ok(())
+                } else {
+                    self.reduce_order(ord_pos, leaves_qty - qty, is_buy, rb_crank, open_orders)
                 }
-                Some(pos) => pos,
-            };
-            self.orders[new_order_pos as usize].order = new_order;
-            self.new_head(new_order_pos);
+            }
         }
-        // Insert in constant time O(1)
-        else if self.is_worse_than_worst(new_order, is_buy) {
-            // We reject the order if there are no next tombstone
-            let pos = self.next_tombstone().unwrap();
-            self.orders[pos as usize].order = new_order;
-            self.new_tail(pos);
+    }
+
+    /// Walks the side without mutating it and returns the quantity an
+    /// incoming order could fill at or better than its limit, capped at its
+    /// leaves quantity. Expired orders are skipped and own orders never fill,
+    /// following `prevent_self_trade`.
+    pub fn fillable_qty(&self, new_order: &Order, is_buy: bool, now: i64) -> u64 {
+        let mut fillable = 0u64;
+        let mut leaves_qty = new_order.get_leaves_qty();
+        if self.is_empty() {
+            return fillable;
         }
-        // Insert in linear time O(n)
-        else {
-            let mut pos = self.head;
-            loop {
-                let other = &self.orders[pos as usize].order;
-                if new_order.is_better_than(other, is_buy) {
-                    let new_order_pos = match self.next_tombstone() {
-                        None => {
-                            // Cancel tail
-                            let tail = self.tail;
-                            let removed_order = self.remove_order(self.tail);
-                            rb_crank.insert(
-                                self.vault,
-                                removed_order.payment_acc,
-                                is_buy,
-                                removed_order.maker,
-                                removed_order.get_leaves_qty(),
-                                removed_order.limit,
-                            );
-                            tail
-                        }
-                        Some(tombstone_pos) => tombstone_pos,
-                    };
-                    self.orders[new_order_pos as usize].order = new_order;
-                    self.new_before(new_order_pos, pos);
-                    break;
+        let mut pos = self.header.head;
+        while leaves_qty > 0 {
+            let order = &self.orders[pos as usize].order;
+            let is_match = match is_buy {
+                true => order.limit <= new_order.limit,
+                false => order.limit >= new_order.limit,
+            };
+            if !is_match {
+                break;
+            }
+
+            let qty = order.get_leaves_qty().min(leaves_qty);
+            if order.is_expired(now) {
+                // Cancelled by matching without a fill.
+            } else if order.maker == new_order.maker {
+                match new_order.get_self_trade_behavior() {
+                    SelfTradeBehavior::AbortTransaction => break,
+                    SelfTradeBehavior::CancelProvide => {}
+                    SelfTradeBehavior::DecrementAndCancel => leaves_qty -= qty,
                 }
+            } else {
+                fillable += qty;
+                leaves_qty -= qty;
+            }
+            pos = match self.next_order(pos) {
+                None => break,
+                Some(next_pos) => next_pos,
+            };
+        }
+        fillable
+    }
+
+    /// Finds the first order of a maker with a client order id.
+    pub fn find_by_client_order_id(&mut self, maker: &Pubkey, client_order_id: u64) -> Option<u16> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut pos = self.header.head;
+        loop {
+            let order = &self.orders[pos as usize].order;
+            if order.maker == *maker && order.get_client_order_id() == client_order_id {
+                return Some(pos);
+            }
+            pos = self.next_order(pos)?;
+        }
+    }
+
+    /// Cancels the orders of a maker within a price range, starting at `pos`.
+    ///
+    /// Visits at most `budget` orders and decrements it for every visited order.
+    /// Returns the position to resume from if the budget ran out before the
+    /// end of the side.
+    #[allow(clippy::too_many_arguments)]
+    pub fn cancel_orders(
+        &mut self,
+        maker: &Pubkey,
+        is_buy: bool,
+        limits: RangeInclusive<u64>,
+        mut pos: u16,
+        budget: &mut u16,
+        rb_crank: &mut RingBufferCrank,
+        mut open_orders: Option<&mut OpenOrders>,
+        clock: &Clock,
+    ) -> Result<Option<u16>> {
+        if self.is_empty() {
+            return Ok(None);
+        }
+        loop {
+            if *budget == 0 {
+                return Ok(Some(pos));
+            }
+            *budget -= 1;
 
-                pos = match self.next_order(pos) {
-                    None => panic!("Failed to find pos for new order in linear time!"),
-                    Some(next_pos) => next_pos,
+            let next_pos = self.next_order(pos);
+            let order = &self.orders[pos as usize].order;
+            if order.maker == *maker && limits.contains(&order.limit) {
+                self.cancel_order(
+                    pos,
+                    is_buy,
+                    CancelReason::Maker,
+                    rb_crank,
+                    open_orders.as_deref_mut(),
+                    clock,
+                )?;
+            }
+            pos = match next_pos {
+                None => return Ok(None),
+                Some(next_pos) => next_pos,
+            };
+        }
+    }
+
+    /// Cancels expired orders from the head, visiting at most budget orders,
+    /// and refunds them through the crank. Returns the number of orders
+    /// cancelled.
+    pub fn prune_expired(
+        &mut self,
+        is_buy: bool,
+        budget: &mut u16,
+        rb_crank: &mut RingBufferCrank,
+        clock: &Clock,
+    ) -> Result<u16> {
+        let mut pruned = 0;
+        if self.is_empty() {
+            return Ok(pruned);
+        }
+        let mut pos = self.header.head;
+        while *budget > 0 {
+            *budget -= 1;
+
+            let next_pos = self.next_order(pos);
+            if self.orders[pos as usize]
+                .order
+                .is_expired(clock.unix_timestamp)
+            {
+                self.cancel_order(pos, is_buy, CancelReason::Expired, rb_crank, None, clock)?;
+                pruned += 1;
+            }
+            pos = match next_pos {
+                None => break,
+                Some(next_pos) => next_pos,
+            };
+        }
+        Ok(pruned)
+    }
+
+    /// Inserts an order behind the orders at the same or a better price.
+    ///
+    /// Finds the place of the order in logarithmic time through the price
+    /// levels. A full side rejects the order unless it beats the worst order
+    /// by the min eviction gap, which is then cancelled and returned.
+    pub fn insert_order(
+        &mut self,
+        new_order: Order,
+        is_buy: bool,
+        min_eviction_gap: Option<u64>,
+        rb_crank: &mut RingBufferCrank,
+        open_orders: Option<&mut OpenOrders>,
+        clock: &Clock,
+    ) -> Result<Option<Order>> {
+        let (new_order_pos, evicted_order) = match self.next_tombstone() {
+            Some(pos) => (pos, None),
+            None => {
+                let tail = self.header.tail;
+                let worst = self.orders[tail as usize].order.limit;
+                let gap = min_eviction_gap.ok_or(ErrorCode::BookFull)?;
+                let is_better_by_gap = match is_buy {
+                    true => new_order.limit >= worst.saturating_add(gap),
+                    false => new_order.limit.saturating_add(gap) <= worst,
                 };
+                require!(is_better_by_gap, ErrorCode::BookFull);
+                let evicted_order = self.cancel_order(
+                    tail,
+                    is_buy,
+                    CancelReason::Evicted,
+                    rb_crank,
+                    open_orders,
+                    clock,
+                )?;
+                // The cancel freed the tail slot last, take it back off the free list.
+                self.header.tombstone = self.orders[tail as usize].next;
+                (tail, Some(evicted_order))
+            }
+        };
+        self.orders[new_order_pos as usize].order = new_order;
+
+        let leaves_qty = new_order.get_leaves_qty();
+        match self.levels.find(new_order.limit) {
+            // Join the queue of the level.
+            Some(level_pos) => {
+                let level = self.levels.get_mut(level_pos);
+                let last = level.last;
+                level.last = new_order_pos;
+                level.order_count += 1;
+                level.total_qty += leaves_qty;
+                self.orders[new_order_pos as usize].level = level_pos;
+                self.new_after(new_order_pos, last);
+            }
+            // Open a level ahead of the next worse one.
+            None => {
+                let before = self
+                    .levels
+                    .next_worse(new_order.limit, is_buy)
+                    .map(|level_pos| self.levels.get(level_pos).first);
+                let is_empty = self.levels.is_empty();
+                let level_pos = self
+                    .levels
+                    .insert(new_order.limit, new_order_pos, leaves_qty);
+                self.orders[new_order_pos as usize].level = level_pos;
+                match before {
+                    _ if is_empty => {
+                        self.orders[new_order_pos as usize].prev = new_order_pos;
+                        self.orders[new_order_pos as usize].next = new_order_pos;
+                        self.header.head = new_order_pos;
+                        self.header.tail = new_order_pos;
+                    }
+                    Some(before) if self.is_head(before) => self.new_head(new_order_pos),
+                    Some(before) => self.new_before(new_order_pos, before),
+                    None => self.new_tail(new_order_pos),
+                }
             }
         }
+        Ok(evicted_order)
     }
 }
@@ -1 +1,10 @@
-pub const ADMIN_PUBKEY_STR: &str = "FpLbdDnS61viAgJtSXHPKBuvKYRFQ7yc6rzuaLSy1MTc";
\ No newline at end of file
+pub const ADMIN_PUBKEY_STR: &str = "FpLbdDnS61viAgJtSXHPKBuvKYRFQ7yc6rzuaLSy1MTc";
+
+/// Admin key as a `Pubkey`, seeds the master config address.
+pub mod admin {
+    anchor_lang::declare_id!("FpLbdDnS61viAgJtSXHPKBuvKYRFQ7yc6rzuaLSy1MTc");
+}
+
+pub const FEE_BPS_DENOMINATOR: u64 = 10_000;
+pub const MAX_MAKER_FEE_BPS: u8 = 100;
+pub const MAX_TAKER_FEE_BPS: u8 = 100;
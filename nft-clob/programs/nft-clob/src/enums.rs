@@ -7,7 +7,7 @@ pub enum BookSide {
     Buy,
 }
 
-/// All order types must have a limit price upon placement.
+/// All order types except market orders must have a limit price upon placement.
 #[derive(Debug, Copy, Clone, AnchorSerialize, AnchorDeserialize, Eq, PartialEq)]
 #[repr(u8)]
 pub enum OrderType {
@@ -26,5 +26,103 @@ pub enum OrderType {
     /// cancelled if the price entered would execute immediately e.g. a buy
     /// limit order above market price, entered as a maker-only, would be
     /// rejected and cancelled.
-    MO = 3
-}
\ No newline at end of file
+    MO = 3,
+    /// A Market order (MKT) is an IOC order without a limit price. It
+    /// executes against the best prices available and never rests.
+    MKT = 4,
+    /// A Good-Til-Time order (GTT) rests like a GTC order until its expiry
+    /// unix timestamp. Expired orders are skipped by matching and pruned.
+    GTT = 5,
+    /// A Maker-Only Slide order (MOS) is a MO order that is repriced one tick
+    /// behind the opposite best offer instead of being rejected.
+    MOS = 6,
+}
+
+impl OrderType {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => OrderType::FOK,
+            2 => OrderType::IOC,
+            3 => OrderType::MO,
+            4 => OrderType::MKT,
+            5 => OrderType::GTT,
+            6 => OrderType::MOS,
+            _ => OrderType::GTC,
+        }
+    }
+}
+
+/// What happens when an incoming order would match a resting order of the
+/// same maker.
+#[derive(Debug, Copy, Clone, AnchorSerialize, AnchorDeserialize, Eq, PartialEq)]
+#[repr(u8)]
+pub enum SelfTradeBehavior {
+    /// Both orders are decremented by the overlapping quantity without a
+    /// trade. The resting order is cancelled once nothing is left.
+    DecrementAndCancel = 0,
+    /// The resting order is cancelled and matching continues.
+    CancelProvide = 1,
+    /// The transaction fails.
+    AbortTransaction = 2,
+}
+
+impl SelfTradeBehavior {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => SelfTradeBehavior::CancelProvide,
+            2 => SelfTradeBehavior::AbortTransaction,
+            _ => SelfTradeBehavior::DecrementAndCancel,
+        }
+    }
+}
+
+/// What happens to a new order when its side of the book is full.
+#[derive(Debug, Default, Copy, Clone, AnchorSerialize, AnchorDeserialize, Eq, PartialEq)]
+#[repr(u8)]
+pub enum OverflowPolicy {
+    /// The new order is rejected.
+    #[default]
+    Reject = 0,
+    /// The worst resting order is cancelled if the new order is better by at
+    /// least the minimum number of ticks, otherwise the new order is rejected.
+    Evict = 1,
+}
+
+impl OverflowPolicy {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => OverflowPolicy::Evict,
+            _ => OverflowPolicy::Reject,
+        }
+    }
+}
+
+/// Why an order left the book before it was filled.
+#[derive(Debug, Copy, Clone, AnchorSerialize, AnchorDeserialize, Eq, PartialEq)]
+#[repr(u8)]
+pub enum CancelReason {
+    /// Cancelled by its maker.
+    Maker = 0,
+    /// Expired, cancelled by matching or pruning.
+    Expired = 1,
+    /// Cancelled by self-trade prevention.
+    SelfTrade = 2,
+    /// Evicted from a full side by a better order.
+    Evicted = 3,
+    /// Unfilled rest of an IOC or market order.
+    Unfilled = 4,
+    /// Replaced by an amended order with a new order id.
+    Amended = 5,
+}
+
+/// Why the book refused a new order.
+#[derive(Debug, Copy, Clone, AnchorSerialize, AnchorDeserialize, Eq, PartialEq)]
+#[repr(u8)]
+pub enum RejectReason {
+    /// Maker-only order would have crossed the opposite best offer.
+    MakerOnlyCrossed = 0,
+    /// Fill-or-kill order could not be filled in its entirety.
+    FillOrKillUnfilled = 1,
+    /// Side is full and the order does not qualify for eviction.
+    BookFull = 2,
+}
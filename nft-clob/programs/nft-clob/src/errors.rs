@@ -20,4 +20,68 @@ pub enum ErrorCode {
     UnauthorizedOrderCancellation,
     #[msg("Order is tombstone!")]
     TombstoneOrder,
+    #[msg("NFT is not verified by the creators of the pool.")]
+    UnverifiedNft,
+    #[msg("NFT is not held by the pool.")]
+    NftNotInPool,
+    #[msg("Pool holds no NFTs.")]
+    NftPoolEmpty,
+    #[msg("NFT pool inventory is full.")]
+    NftInventoryFull,
+    #[msg("NFT pool needs verified creators or a collection.")]
+    NftPoolWithoutCriteria,
+    #[msg("Fee exceeds the maximum BPS.")]
+    FeeTooHigh,
+    #[msg("Client is already fee exempt.")]
+    AlreadyFeeExempt,
+    #[msg("Client is not fee exempt.")]
+    NotFeeExempt,
+    #[msg("Order at position has a different order id.")]
+    OrderIdMismatch,
+    #[msg("Order not found.")]
+    OrderNotFound,
+    #[msg("Amend must change the limit or quantity to a non-zero value.")]
+    InvalidAmend,
+    #[msg("rb-crank filled up. Crank faster to accept new orders!")]
+    RbCrankFull,
+    #[msg("Remaining accounts must be pairs of vault and token account.")]
+    InvalidCrankAccounts,
+    #[msg("Open orders not found.")]
+    OpenOrdersNotFound,
+    #[msg("Order would trade with an order of the same maker.")]
+    SelfTrade,
+    #[msg("Quote budget must be positive and is only for IOC or market buys.")]
+    InvalidQuoteBudget,
+    #[msg("Expiry must be in the future and is only for GTT orders.")]
+    InvalidExpiry,
+    #[msg("Tick size, lot size and min size must be positive and min size at most max size.")]
+    InvalidMarketParams,
+    #[msg("Limit price must be positive.")]
+    ZeroPrice,
+    #[msg("Order size must be positive.")]
+    ZeroSize,
+    #[msg("Limit price is not a multiple of the tick size.")]
+    InvalidTickSize,
+    #[msg("Order size is not a multiple of the lot size.")]
+    InvalidLotSize,
+    #[msg("Order size is below the minimum size.")]
+    OrderSizeTooSmall,
+    #[msg("Order size is above the maximum size.")]
+    OrderSizeTooLarge,
+    #[msg("Book side is full and the order does not qualify for eviction.")]
+    BookFull,
+    #[msg("Account is too small for its capacity.")]
+    InvalidCapacity,
+    #[msg("Mint is not an NFT.")]
+    NotAnNft,
+    #[msg("Depositor does not match the depositor of the NFT.")]
+    WrongDepositor,
+    #[msg("Client order id must be set.")]
+    ZeroClientOrderId,
+    #[msg("Crank reward overflows.")]
+    CrankRewardOverflow,
+    #[msg("Open orders still hold funds or back resting orders.")]
+    OpenOrdersInUse,
+    #[msg("Fill amounts overflow.")]
+    FillOverflow,
 }
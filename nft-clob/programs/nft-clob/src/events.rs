@@ -0,0 +1,215 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    account_states::Order,
+    enums::{CancelReason, OrderType, RejectReason},
+};
+
+/// A resting order was cancelled to make room for a better order on a full
+/// side of the book. Its deposit is refunded like on cancellation.
+#[event]
+pub struct OrderEvicted {
+    pub instrmt: Pubkey,      // Instrument of the book.
+    pub maker: Pubkey,        // Maker of the evicted order.
+    pub order_id: u64,        // Evicted order.
+    pub client_order_id: u64, // Client order id of the evicted order, 0 if not set.
+    pub is_buy: bool,         // Side of the evicted order.
+    pub limit: u64,           // Limit of the evicted order.
+    pub filled_qty: u64,      // Quantity filled before the eviction.
+    pub leaves_qty: u64,      // Quantity cancelled.
+    pub evicted_by: u64,      // Order id of the new order.
+    pub slot: u64,            // Slot of the eviction.
+}
+
+/// A new order was accepted by the book and assigned an order id, before
+/// matching. Amended orders are accepted again under a new order id.
+#[event]
+pub struct OrderAccepted {
+    pub instrmt: Pubkey,       // Instrument of the book.
+    pub maker: Pubkey,         // Maker of the order.
+    pub order_id: u64,         // Order id assigned by the book.
+    pub client_order_id: u64,  // Client order id, 0 if not set.
+    pub is_buy: bool,          // Side of the order.
+    pub order_type: OrderType, // Order type.
+    pub limit: u64,            // Limit, after sliding for maker-only slide orders.
+    pub qty: u64,              // Quantity ordered.
+    pub slot: u64,             // Slot of acceptance.
+}
+
+/// A fill left part of an order open.
+#[event]
+pub struct OrderPartiallyFilled {
+    pub instrmt: Pubkey,      // Instrument of the book.
+    pub maker: Pubkey,        // Maker of the order.
+    pub order_id: u64,        // Order filled.
+    pub client_order_id: u64, // Client order id, 0 if not set.
+    pub is_buy: bool,         // Side of the order.
+    pub price: u64,           // Price of the fill.
+    pub filled_qty: u64,      // Quantity of the fill.
+    pub leaves_qty: u64,      // Quantity still open.
+    pub slot: u64,            // Slot of the fill.
+}
+
+/// The last fill of an order, nothing is left open.
+#[event]
+pub struct OrderFilled {
+    pub instrmt: Pubkey,      // Instrument of the book.
+    pub maker: Pubkey,        // Maker of the order.
+    pub order_id: u64,        // Order filled.
+    pub client_order_id: u64, // Client order id, 0 if not set.
+    pub is_buy: bool,         // Side of the order.
+    pub price: u64,           // Price of the fill.
+    pub filled_qty: u64,      // Quantity of the fill.
+    pub leaves_qty: u64,      // Always 0.
+    pub slot: u64,            // Slot of the fill.
+}
+
+/// An order stopped before it was filled, its deposit is refunded.
+#[event]
+pub struct OrderCancelled {
+    pub instrmt: Pubkey,      // Instrument of the book.
+    pub maker: Pubkey,        // Maker of the order.
+    pub order_id: u64,        // Order cancelled.
+    pub client_order_id: u64, // Client order id, 0 if not set.
+    pub is_buy: bool,         // Side of the order.
+    pub limit: u64,           // Limit of the order.
+    pub filled_qty: u64,      // Quantity filled before the cancellation.
+    pub leaves_qty: u64,      // Quantity cancelled.
+    pub reason: CancelReason, // Why the order was cancelled.
+    pub slot: u64,            // Slot of the cancellation.
+}
+
+/// The book refused a new order. The transaction fails, hence the event is
+/// only found in the logs of the failed transaction.
+#[event]
+pub struct OrderRejected {
+    pub instrmt: Pubkey,      // Instrument of the book.
+    pub maker: Pubkey,        // Maker of the order.
+    pub client_order_id: u64, // Client order id, 0 if not set.
+    pub is_buy: bool,         // Side of the order.
+    pub limit: u64,           // Limit of the order.
+    pub qty: u64,             // Quantity ordered.
+    pub reason: RejectReason, // Why the order was rejected.
+    pub slot: u64,            // Slot of the rejection.
+}
+
+impl OrderEvicted {
+    pub fn new(instrmt: Pubkey, order: &Order, is_buy: bool, evicted_by: u64, slot: u64) -> Self {
+        Self {
+            instrmt,
+            maker: order.maker,
+            order_id: order.get_order_id(),
+            client_order_id: order.get_client_order_id(),
+            is_buy,
+            limit: order.limit,
+            filled_qty: order.get_cum_qty(),
+            leaves_qty: order.get_leaves_qty(),
+            evicted_by,
+            slot,
+        }
+    }
+}
+
+impl OrderAccepted {
+    pub fn new(
+        instrmt: Pubkey,
+        order: &Order,
+        is_buy: bool,
+        order_type: OrderType,
+        slot: u64,
+    ) -> Self {
+        Self {
+            instrmt,
+            maker: order.maker,
+            order_id: order.get_order_id(),
+            client_order_id: order.get_client_order_id(),
+            is_buy,
+            order_type,
+            limit: order.limit,
+            qty: order.get_leaves_qty(),
+            slot,
+        }
+    }
+}
+
+impl OrderCancelled {
+    /// Cancellation of the leaves quantity of an order.
+    pub fn new(
+        instrmt: Pubkey,
+        order: &Order,
+        is_buy: bool,
+        reason: CancelReason,
+        slot: u64,
+    ) -> Self {
+        Self {
+            instrmt,
+            maker: order.maker,
+            order_id: order.get_order_id(),
+            client_order_id: order.get_client_order_id(),
+            is_buy,
+            limit: order.limit,
+            filled_qty: order.get_cum_qty(),
+            leaves_qty: order.get_leaves_qty(),
+            reason,
+            slot,
+        }
+    }
+}
+
+impl OrderRejected {
+    pub fn new(
+        instrmt: Pubkey,
+        order: &Order,
+        is_buy: bool,
+        reason: RejectReason,
+        slot: u64,
+    ) -> Self {
+        Self {
+            instrmt,
+            maker: order.maker,
+            client_order_id: order.get_client_order_id(),
+            is_buy,
+            limit: order.limit,
+            qty: order.get_leaves_qty(),
+            reason,
+            slot,
+        }
+    }
+}
+
+/// Emits the fill of an order after the fill was applied to it.
+pub fn emit_fill(
+    instrmt: Pubkey,
+    order: &Order,
+    is_buy: bool,
+    price: u64,
+    filled_qty: u64,
+    slot: u64,
+) {
+    let leaves_qty = order.get_leaves_qty();
+    if leaves_qty == 0 {
+        emit!(OrderFilled {
+            instrmt,
+            maker: order.maker,
+            order_id: order.get_order_id(),
+            client_order_id: order.get_client_order_id(),
+            is_buy,
+            price,
+            filled_qty,
+            leaves_qty,
+            slot,
+        });
+    } else {
+        emit!(OrderPartiallyFilled {
+            instrmt,
+            maker: order.maker,
+            order_id: order.get_order_id(),
+            client_order_id: order.get_client_order_id(),
+            is_buy,
+            price,
+            filled_qty,
+            leaves_qty,
+            slot,
+        });
+    }
+}
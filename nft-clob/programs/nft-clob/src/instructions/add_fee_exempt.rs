@@ -0,0 +1,43 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+use crate::constants::ADMIN_PUBKEY_STR;
+use crate::errors::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddFeeExemptIx {
+    pub client: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct AddFeeExemptCtx<'info> {
+    #[account(
+        mut,
+        constraint = admin.key.to_string() == ADMIN_PUBKEY_STR @ ErrorCode::NotAdmin
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"master-cfg", admin.key().as_ref()],
+        bump = master_cfg.bump,
+        realloc = MasterCfg::space(master_cfg.fee_exempt.len() + 1),
+        realloc::payer = admin,
+        realloc::zero = false,
+    )]
+    pub master_cfg: Box<Account<'info, MasterCfg>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<AddFeeExemptCtx>, ix: AddFeeExemptIx) -> Result<()> {
+    let master_cfg = &mut ctx.accounts.master_cfg;
+
+    require!(
+        !master_cfg.fee_exempt.contains(&ix.client),
+        ErrorCode::AlreadyFeeExempt
+    );
+    master_cfg.fee_exempt.push(ix.client);
+
+    Ok(())
+}
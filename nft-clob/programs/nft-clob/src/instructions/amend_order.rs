@@ -0,0 +1,273 @@
+use std::cmp::Ordering;
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::account_states::*;
+use crate::enums::CancelReason;
+use crate::errors::ErrorCode;
+use crate::events::OrderCancelled;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AmendOrderIx {
+    pub order_pos: u16,
+    pub order_id: u64,
+    pub is_buy: bool,
+    pub limit: Option<u64>, // New limit, unchanged if not set.
+    pub size: Option<u64>,  // New leaves quantity, unchanged if not set.
+}
+
+#[derive(Accounts)]
+pub struct AmendOrderCtx<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"instrmt", book.key().as_ref()],
+        bump = instrmt.bumps.instrmt_bump,
+        constraint = instrmt.book == book.key(),
+        constraint = instrmt.base_vault == base_vault.key(),
+        constraint = instrmt.quote_vault == quote_vault.key(),
+        constraint = instrmt.base_mint == base_user_token_account.mint,
+        constraint = instrmt.quote_mint == quote_user_token_account.mint,
+        constraint = instrmt.rb_filled_exec_reports == rb_filled_exec_reports.key(),
+    )]
+    pub instrmt: Box<Account<'info, Instrmt>>,
+
+    #[account(
+        constraint = instrmt_grp.key() == instrmt.instrmt_grp
+    )]
+    pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
+
+    #[account(
+        mut,
+        seeds = [b"crank-reward-pool", instrmt_grp.key().as_ref()],
+        bump = crank_reward_pool.bump,
+    )]
+    pub crank_reward_pool: Box<Account<'info, CrankRewardPool>>,
+
+    #[account(mut)]
+    pub rb_filled_exec_reports: AccountLoader<'info, RingBufferFilledExecReportHeader>,
+
+    #[account(mut, constraint = rb_crank.load()?.instrmt_grp == instrmt.instrmt_grp)]
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
+
+    #[account(mut)]
+    pub base_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub quote_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, constraint = base_user_token_account.owner == authority.key())]
+    pub base_user_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, constraint = quote_user_token_account.owner == authority.key())]
+    pub quote_user_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub book: AccountLoader<'info, BookHeader>,
+
+    /// Required to amend orders placed with the open orders of the authority.
+    #[account(
+        mut,
+        seeds = [b"open-orders", instrmt.key().as_ref(), authority.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Box<Account<'info, OpenOrders>>>,
+
+    pub system_program: Program<'info, System>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> AmendOrderCtx<'info> {
+    /// Moves the difference between the deposit held and the deposit required
+    /// for the amended order between user and vault.
+    fn settle_deposit(
+        &self,
+        vault: &Account<'info, TokenAccount>,
+        user: &Account<'info, TokenAccount>,
+        deposit_held: u64,
+        deposit_required: u64,
+    ) -> Result<()> {
+        match deposit_required.cmp(&deposit_held) {
+            Ordering::Greater => {
+                let cpi_accounts = Transfer {
+                    from: user.to_account_info(),
+                    to: vault.to_account_info(),
+                    authority: self.authority.to_account_info(),
+                };
+                let cpi_context =
+                    CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+                token::transfer(cpi_context, deposit_required - deposit_held)
+            }
+            Ordering::Less => {
+                self.transfer_from_vault(vault, user, deposit_held - deposit_required)
+            }
+            Ordering::Equal => Ok(()),
+        }
+    }
+
+    fn transfer_from_vault(
+        &self,
+        vault: &Account<'info, TokenAccount>,
+        user: &Account<'info, TokenAccount>,
+        amount: u64,
+    ) -> Result<()> {
+        let instrmt_grp_seeds = &[
+            b"instrmt-grp".as_ref(),
+            self.instrmt_grp.admin.as_ref(),
+            &[self.instrmt_grp.bump],
+        ];
+
+        let signer = &[&instrmt_grp_seeds[..]];
+
+        let cpi_accounts = Transfer {
+            from: vault.to_account_info(),
+            to: user.to_account_info(),
+            authority: self.instrmt_grp.to_account_info(),
+        };
+        let cpi_context =
+            CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer);
+        token::transfer(cpi_context, amount)
+    }
+
+    /// Funds the keepers processing the cranks queued by the amend. The
+    /// crank refunding a resting order was paid when it was placed.
+    fn charge_crank_reward(&self, cranks: u16) -> Result<()> {
+        CrankRewardPool::charge_reward(
+            &self.crank_reward_pool.to_account_info(),
+            &self.authority.to_account_info(),
+            &self.system_program.to_account_info(),
+            CrankRewardPool::reward(self.instrmt_grp.crank_reward_lamports, cranks as u64)?,
+        )
+    }
+}
+
+/// Reducing the size at the same limit keeps queue priority and refunds the
+/// difference through the crank, or to the open orders of the order. Any
+/// other change cancels and re-places the
+/// order with its original order type, which may match immediately unless
+/// it is maker-only, and gets a new order id.
+pub fn handler(ctx: Context<AmendOrderCtx>, ix: AmendOrderIx) -> Result<()> {
+    let mut book_data = ctx.accounts.book.as_ref().try_borrow_mut_data()?;
+    let book = &mut Book::load_mut(&mut book_data)?;
+    let mut rb_filled_exec_reports_data = ctx
+        .accounts
+        .rb_filled_exec_reports
+        .as_ref()
+        .try_borrow_mut_data()?;
+    let rb_filled_exec_reports =
+        &mut RingBufferFilledExecReport::load_mut(&mut rb_filled_exec_reports_data)?;
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+    let clock = Clock::get()?;
+
+    let side = match ix.is_buy {
+        true => &mut book.bids,
+        false => &mut book.asks,
+    };
+
+    require!(!side.is_tombstone(ix.order_pos), ErrorCode::TombstoneOrder);
+
+    let order = side.orders[ix.order_pos as usize].order;
+    require!(
+        order.get_order_id() == ix.order_id,
+        ErrorCode::OrderIdMismatch
+    );
+    require!(
+        order.maker == ctx.accounts.authority.key(),
+        ErrorCode::UnauthorizedOrderCancellation
+    );
+    require!(
+        !order.uses_open_orders() || ctx.accounts.open_orders.is_some(),
+        ErrorCode::OpenOrdersNotFound
+    );
+    let mut open_orders = ctx
+        .accounts
+        .open_orders
+        .as_deref_mut()
+        .map(|open_orders| &mut **open_orders);
+
+    let limit = ix.limit.unwrap_or(order.limit);
+    let size = ix.size.unwrap_or(order.get_leaves_qty());
+    if let Some(limit) = ix.limit {
+        ctx.accounts.instrmt.validate_limit(limit)?;
+    }
+    if let Some(size) = ix.size {
+        ctx.accounts.instrmt.validate_size(size)?;
+    }
+    require!(
+        limit != order.limit || size != order.get_leaves_qty(),
+        ErrorCode::InvalidAmend
+    );
+
+    let cranks_queued = rb_crank.len();
+    if limit == order.limit && size < order.get_leaves_qty() {
+        side.reduce_order(ix.order_pos, size, ix.is_buy, rb_crank, open_orders)?;
+        return ctx
+            .accounts
+            .charge_crank_reward(rb_crank.len() - cranks_queued);
+    }
+
+    let removed_order = side.remove_order(ix.order_pos);
+    let deposit_held = removed_order.get_leaves_deposit(ix.is_buy).unwrap();
+    emit!(OrderCancelled::new(
+        ctx.accounts.instrmt.key(),
+        &removed_order,
+        ix.is_buy,
+        CancelReason::Amended,
+        clock.slot
+    ));
+    book.refresh_best_offers();
+
+    let order = book.new_limit(
+        removed_order.amend(limit, size, clock.unix_timestamp)?,
+        removed_order.get_order_type(),
+        ix.is_buy,
+        rb_filled_exec_reports,
+        rb_crank,
+        open_orders.as_deref_mut(),
+        &clock,
+    )?;
+
+    // Buyers deposit quote including fees and receive base if partially filled.
+    // Sellers deposit base and receive quote minus fees if partially filled.
+    let (deposit_required, proceeds) = order.get_settlement(ix.is_buy).unwrap();
+
+    // Open orders release the deposit held and fund the new deposit from
+    // their free balance first, proceeds stay for the owner to settle.
+    let (deposit_held, user_proceeds) = match open_orders {
+        Some(open_orders) if order.uses_open_orders() => {
+            open_orders.unlock(ix.is_buy, deposit_held);
+            let free_deposit = open_orders.take_free(ix.is_buy, deposit_required);
+            open_orders.lock(ix.is_buy, order.get_leaves_deposit(ix.is_buy).unwrap());
+            open_orders.credit_proceeds(ix.is_buy, proceeds);
+            (free_deposit, 0)
+        }
+        _ => (deposit_held, proceeds),
+    };
+
+    let accounts = &ctx.accounts;
+    accounts.charge_crank_reward(rb_crank.len() - cranks_queued)?;
+    let (deposit_vault, deposit_user, payout_vault, payout_user) = match ix.is_buy {
+        true => (
+            &accounts.quote_vault,
+            &accounts.quote_user_token_account,
+            &accounts.base_vault,
+            &accounts.base_user_token_account,
+        ),
+        false => (
+            &accounts.base_vault,
+            &accounts.base_user_token_account,
+            &accounts.quote_vault,
+            &accounts.quote_user_token_account,
+        ),
+    };
+
+    accounts.settle_deposit(deposit_vault, deposit_user, deposit_held, deposit_required)?;
+    if user_proceeds > 0 {
+        accounts.transfer_from_vault(payout_vault, payout_user, user_proceeds)?;
+    }
+    Ok(())
+}
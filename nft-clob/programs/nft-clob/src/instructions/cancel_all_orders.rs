@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::{Book, RingBufferCrank};
+
+use super::CancelOrderCtx;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelAllOrdersIx {
+    pub is_buy: Option<bool>,            // Both sides if not set.
+    pub min_limit: Option<u64>,          // Lowest limit to cancel.
+    pub max_limit: Option<u64>,          // Highest limit to cancel.
+    pub cursor: Option<CancelAllCursor>, // Resume from a previous call.
+    pub max_orders: u16,                 // Max orders to visit to stay within compute limits.
+}
+
+/// Position to resume cancelling from, returned when `max_orders` is reached.
+///
+/// If the order at the position changed in the meantime we restart from the
+/// head of the side, which only costs compute as cancelled orders are gone.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct CancelAllCursor {
+    pub is_buy: bool,
+    pub order_pos: u16,
+    pub order_id: u64,
+}
+
+pub fn handler(
+    ctx: Context<CancelOrderCtx>,
+    ix: CancelAllOrdersIx,
+) -> Result<Option<CancelAllCursor>> {
+    let mut book_data = ctx.accounts.book.as_ref().try_borrow_mut_data()?;
+    let book = &mut Book::load_mut(&mut book_data)?;
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+    let mut open_orders = ctx
+        .accounts
+        .open_orders
+        .as_deref_mut()
+        .map(|open_orders| &mut **open_orders);
+    let clock = Clock::get()?;
+    let maker = ctx.accounts.authority.key();
+    let limits = ix.min_limit.unwrap_or(0)..=ix.max_limit.unwrap_or(u64::MAX);
+
+    let sides: &[bool] = match ix.is_buy {
+        Some(true) => &[true],
+        Some(false) => &[false],
+        None => &[true, false],
+    };
+
+    let mut budget = ix.max_orders;
+    let mut cursor = ix.cursor;
+    let mut next_cursor = None;
+
+    for &is_buy in sides {
+        let side = match is_buy {
+            true => &mut book.bids,
+            false => &mut book.asks,
+        };
+
+        let mut pos = side.head;
+        if let Some(cursor) = cursor.take() {
+            if cursor.is_buy != is_buy {
+                continue; // side already done
+            }
+            let order = &side.orders[cursor.order_pos as usize].order;
+            if !order.is_tombstone() && order.get_order_id() == cursor.order_id {
+                pos = cursor.order_pos;
+            }
+        }
+
+        if let Some(order_pos) = side.cancel_orders(
+            &maker,
+            is_buy,
+            limits.clone(),
+            pos,
+            &mut budget,
+            rb_crank,
+            open_orders.as_deref_mut(),
+            &clock,
+        )? {
+            next_cursor = Some(CancelAllCursor {
+                is_buy,
+                order_pos,
+                order_id: side.orders[order_pos as usize].order.get_order_id(),
+            });
+            break;
+        }
+    }
+
+    book.refresh_best_offers();
+    Ok(next_cursor)
+}
@@ -1,11 +1,13 @@
 use anchor_lang::prelude::*;
 
 use crate::account_states::*;
+use crate::enums::CancelReason;
 use crate::errors::ErrorCode;
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct CancelOrderIx {
     pub order_pos: u16,
+    pub order_id: u64,
     pub is_buy: bool,
 }
 
@@ -26,35 +28,55 @@ pub struct CancelOrderCtx<'info> {
     pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
 
     #[account(mut)]
-    pub book: AccountLoader<'info, Book>,
+    pub book: AccountLoader<'info, BookHeader>,
 
     #[account(mut, constraint = rb_crank.load()?.instrmt_grp == instrmt.instrmt_grp)]
-    pub rb_crank: AccountLoader<'info, RingBufferCrank>,
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
+
+    #[account(
+        mut,
+        seeds = [b"open-orders", instrmt.key().as_ref(), authority.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Box<Account<'info, OpenOrders>>>,
 }
 
 pub fn handler(ctx: Context<CancelOrderCtx>, ix: CancelOrderIx) -> Result<()> {
-    let book = &mut ctx.accounts.book.load_mut()?;
-    let rb_crank = &mut ctx.accounts.rb_crank.load_mut()?;
+    let mut book_data = ctx.accounts.book.as_ref().try_borrow_mut_data()?;
+    let book = &mut Book::load_mut(&mut book_data)?;
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+    let open_orders = ctx
+        .accounts
+        .open_orders
+        .as_deref_mut()
+        .map(|open_orders| &mut **open_orders);
+    let clock = Clock::get()?;
 
     let side = match ix.is_buy {
         true => &mut book.bids,
         false => &mut book.asks,
     };
-    
-    require!(!side.is_tombstone(ix.order_pos), ErrorCode::TombstoneOrder);
 
-    let removed_order = side.remove_order(ix.order_pos);
+    require!(!side.is_tombstone(ix.order_pos), ErrorCode::TombstoneOrder);
+    let order = &side.orders[ix.order_pos as usize].order;
     require!(
-        removed_order.maker == ctx.accounts.authority.key(),
+        order.get_order_id() == ix.order_id,
+        ErrorCode::OrderIdMismatch
+    );
+    require!(
+        order.maker == ctx.accounts.authority.key(),
         ErrorCode::UnauthorizedOrderCancellation
     );
-    rb_crank.insert(
-        side.vault,
-        removed_order.payment_acc,
+
+    side.cancel_order(
+        ix.order_pos,
         ix.is_buy,
-        removed_order.maker,
-        removed_order.get_leaves_qty(),
-        removed_order.limit,
-    );
+        CancelReason::Maker,
+        rb_crank,
+        open_orders,
+        &clock,
+    )?;
+    book.refresh_best_offers();
     Ok(())
 }
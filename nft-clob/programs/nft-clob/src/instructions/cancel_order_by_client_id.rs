@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::{Book, RingBufferCrank};
+use crate::enums::CancelReason;
+use crate::errors::ErrorCode;
+
+use super::CancelOrderCtx;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CancelOrderByClientIdIx {
+    pub client_order_id: u64,
+    pub is_buy: bool,
+}
+
+pub fn handler(ctx: Context<CancelOrderCtx>, ix: CancelOrderByClientIdIx) -> Result<()> {
+    let mut book_data = ctx.accounts.book.as_ref().try_borrow_mut_data()?;
+    let book = &mut Book::load_mut(&mut book_data)?;
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+    let open_orders = ctx
+        .accounts
+        .open_orders
+        .as_deref_mut()
+        .map(|open_orders| &mut **open_orders);
+    let clock = Clock::get()?;
+
+    // Zero means no client order id, it must not match unlabelled orders.
+    require!(ix.client_order_id != 0, ErrorCode::ZeroClientOrderId);
+
+    let side = match ix.is_buy {
+        true => &mut book.bids,
+        false => &mut book.asks,
+    };
+
+    let order_pos = side
+        .find_by_client_order_id(&ctx.accounts.authority.key(), ix.client_order_id)
+        .ok_or(ErrorCode::OrderNotFound)?;
+    side.cancel_order(
+        order_pos,
+        ix.is_buy,
+        CancelReason::Maker,
+        rb_crank,
+        open_orders,
+        &clock,
+    )?;
+
+    book.refresh_best_offers();
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct CloseOpenOrdersCtx<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"instrmt", instrmt.book.as_ref()],
+        bump = instrmt.bumps.instrmt_bump,
+    )]
+    pub instrmt: Box<Account<'info, Instrmt>>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"open-orders", instrmt.key().as_ref(), authority.key().as_ref()],
+        bump = open_orders.bump,
+        constraint = open_orders.is_empty() @ ErrorCode::OpenOrdersInUse
+    )]
+    pub open_orders: Box<Account<'info, OpenOrders>>,
+}
+
+/// Closes the open orders of the authority and refunds the rent. Refused
+/// while funds are unsettled or orders rest on the book, including fills and
+/// refunds still waiting in the crank.
+pub fn handler(_ctx: Context<CloseOpenOrdersCtx>) -> Result<()> {
+    Ok(())
+}
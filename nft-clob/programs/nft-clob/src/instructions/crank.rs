@@ -13,45 +13,92 @@ pub struct CrankCtx<'info> {
         constraint = rb_crank.to_account_info().owner == program_id,
         constraint = rb_crank.load()?.instrmt_grp == instrmt_grp.key()
     )]
-    pub rb_crank: AccountLoader<'info, RingBufferCrank>,
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
 
     pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
 
+    #[account(
+        mut,
+        seeds = [b"crank-reward-pool", instrmt_grp.key().as_ref()],
+        bump = crank_reward_pool.bump,
+    )]
+    pub crank_reward_pool: Box<Account<'info, CrankRewardPool>>,
+
     #[account(mut)]
     pub vault: Box<Account<'info, TokenAccount>>,
 
+    /// CHECK: Token account or open orders of the crank, checked when settling.
     #[account(mut)]
-    pub user: Box<Account<'info, TokenAccount>>,
+    pub user: UncheckedAccount<'info>,
 
     pub token_program: Program<'info, Token>,
 }
 
 pub fn handler(ctx: Context<CrankCtx>) -> Result<()> {
-    let rb_crank = &mut ctx.accounts.rb_crank.load_mut()?;
-    let crank = rb_crank.remove_head();
-    
-    require!(!crank.is_empty(), ErrorCode::RbCrankEmpty);
-    require!(crank.get_token_account() == ctx.accounts.user.key(), ErrorCode::WrongTokenAccount);
-    require!(crank.get_vault() == ctx.accounts.vault.key(), ErrorCode::WrongVaultAccount);
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+    let crank = rb_crank.remove_head().ok_or(ErrorCode::RbCrankEmpty)?;
+
+    settle_crank(
+        &crank,
+        &ctx.accounts.instrmt_grp,
+        ctx.accounts.vault.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+    )?;
+
+    CrankRewardPool::pay_reward(
+        &ctx.accounts.crank_reward_pool.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        ctx.accounts.instrmt_grp.crank_reward_lamports,
+    )?;
+    Ok(())
+}
+
+/// Transfers the quantity of a crank from its vault to its token account, or
+/// credits it to the open orders the crank is for and leaves it in the vault.
+pub fn settle_crank<'info>(
+    crank: &Crank,
+    instrmt_grp: &Account<'info, InstrmtGrp>,
+    vault: AccountInfo<'info>,
+    user: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<()> {
+    require!(
+        crank.get_token_account() == user.key(),
+        ErrorCode::WrongTokenAccount
+    );
+    require!(
+        crank.get_vault() == vault.key(),
+        ErrorCode::WrongVaultAccount
+    );
+
+    // Token accounts belong to the token program, open orders to this one.
+    if user.owner == &crate::ID {
+        let mut open_orders = Account::<OpenOrders>::try_from(&user)?;
+        let is_base = crank.get_vault() == open_orders.base_vault;
+        require!(
+            is_base || crank.get_vault() == open_orders.quote_vault,
+            ErrorCode::WrongVaultAccount
+        );
+        open_orders.credit_crank(is_base, crank.get_quantity(), crank.get_released());
+        return open_orders.exit(&crate::ID);
+    }
 
     let instrmt_grp_seeds = &[
         b"instrmt-grp".as_ref(),
-        &ctx.accounts.instrmt_grp.admin.as_ref(),
-        &[ctx.accounts.instrmt_grp.bump],
+        instrmt_grp.admin.as_ref(),
+        &[instrmt_grp.bump],
     ];
 
     let signer = &[&instrmt_grp_seeds[..]];
 
     let cpi_accounts = Transfer {
-        from: ctx.accounts.vault.to_account_info().clone(),
-        to: ctx.accounts.user.to_account_info().clone(),
-        authority: ctx.accounts.instrmt_grp.to_account_info().clone(),
+        from: vault,
+        to: user,
+        authority: instrmt_grp.to_account_info(),
     };
-    let cpi_context = CpiContext::new_with_signer(ctx.accounts.token_program.to_account_info(), cpi_accounts, signer);
+    let cpi_context = CpiContext::new_with_signer(token_program, cpi_accounts, signer);
 
-    token::transfer(
-        cpi_context, crank.get_quantity()
-    )?;
-
-    Ok(())
+    token::transfer(cpi_context, crank.get_quantity())
 }
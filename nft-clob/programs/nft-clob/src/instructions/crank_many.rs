@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+
+use crate::account_states::*;
+use crate::errors::ErrorCode;
+
+use super::settle_crank;
+
+#[derive(Accounts)]
+pub struct CrankManyCtx<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        constraint = rb_crank.to_account_info().owner == program_id,
+        constraint = rb_crank.load()?.instrmt_grp == instrmt_grp.key()
+    )]
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
+
+    pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
+
+    #[account(
+        mut,
+        seeds = [b"crank-reward-pool", instrmt_grp.key().as_ref()],
+        bump = crank_reward_pool.bump,
+    )]
+    pub crank_reward_pool: Box<Account<'info, CrankRewardPool>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Settles one crank per vault and token account or open orders pair in the
+/// remaining accounts, in queue order.
+pub fn handler<'info>(ctx: Context<'_, '_, '_, 'info, CrankManyCtx<'info>>) -> Result<()> {
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+
+    require!(
+        ctx.remaining_accounts.len() % 2 == 0,
+        ErrorCode::InvalidCrankAccounts
+    );
+
+    for accounts in ctx.remaining_accounts.chunks(2) {
+        let crank = rb_crank.remove_head().ok_or(ErrorCode::RbCrankEmpty)?;
+
+        settle_crank(
+            &crank,
+            &ctx.accounts.instrmt_grp,
+            accounts[0].clone(),
+            accounts[1].clone(),
+            ctx.accounts.token_program.to_account_info(),
+        )?;
+    }
+
+    let cranks = (ctx.remaining_accounts.len() / 2) as u64;
+    CrankRewardPool::pay_reward(
+        &ctx.accounts.crank_reward_pool.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        CrankRewardPool::reward(ctx.accounts.instrmt_grp.crank_reward_lamports, cranks)?,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+
+#[derive(Accounts)]
+pub struct CrankQueueDepthCtx<'info> {
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
+}
+
+/// Number of cranks waiting to be processed, for keepers to simulate.
+pub fn handler(ctx: Context<CrankQueueDepthCtx>) -> Result<u16> {
+    Ok(ctx.accounts.rb_crank.load()?.len())
+}
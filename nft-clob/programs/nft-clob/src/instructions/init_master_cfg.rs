@@ -1,7 +1,7 @@
 use anchor_lang::prelude::*;
 
 use crate::account_states::*;
-use crate::constants::ADMIN_PUBKEY_STR;
+use crate::constants::{ADMIN_PUBKEY_STR, MAX_MAKER_FEE_BPS, MAX_TAKER_FEE_BPS};
 use crate::errors::ErrorCode;
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -32,6 +32,9 @@ pub struct InitMasterCfgCtx<'info> {
 }
 
 pub fn handler(ctx: Context<InitMasterCfgCtx>, ix: InitMasterCfgIx) -> Result<()> {
+    require!(ix.maker_fee_bps <= MAX_MAKER_FEE_BPS, ErrorCode::FeeTooHigh);
+    require!(ix.taker_fee_bps <= MAX_TAKER_FEE_BPS, ErrorCode::FeeTooHigh);
+
     let master_cfg = &mut ctx.accounts.master_cfg;
 
     master_cfg.maker_fee_bps = ix.maker_fee_bps;
@@ -1,17 +1,47 @@
+pub mod add_fee_exempt;
+pub mod amend_order;
+pub mod cancel_all_orders;
 pub mod cancel_order;
+pub mod cancel_order_by_client_id;
+pub mod close_open_orders;
 pub mod crank;
+pub mod crank_many;
+pub mod crank_queue_depth;
 pub mod init_master_cfg;
 pub mod new_instrmt;
 pub mod new_instrmt_grp;
 pub mod new_nft_pool;
+pub mod new_open_orders;
 pub mod new_order_single;
+pub mod prune_expired;
+pub mod remove_fee_exempt;
+pub mod settle_funds;
+pub mod swap_ft;
 pub mod swap_nft;
+pub mod sweep_fees;
+pub mod update_crank_reward;
+pub mod update_master_cfg;
 
+pub use add_fee_exempt::*;
+pub use amend_order::*;
+pub use cancel_all_orders::*;
 pub use cancel_order::*;
+pub use cancel_order_by_client_id::*;
+pub use close_open_orders::*;
 pub use crank::*;
+pub use crank_many::*;
+pub use crank_queue_depth::*;
 pub use init_master_cfg::*;
 pub use new_instrmt::*;
 pub use new_instrmt_grp::*;
 pub use new_nft_pool::*;
+pub use new_open_orders::*;
 pub use new_order_single::*;
+pub use prune_expired::*;
+pub use remove_fee_exempt::*;
+pub use settle_funds::*;
+pub use swap_ft::*;
 pub use swap_nft::*;
+pub use sweep_fees::*;
+pub use update_crank_reward::*;
+pub use update_master_cfg::*;
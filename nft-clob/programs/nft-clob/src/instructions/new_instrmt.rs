@@ -2,12 +2,19 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 
 use crate::account_states::*;
+use crate::enums::OverflowPolicy;
 use crate::errors::ErrorCode;
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct NewInstrmtIx {
     pub base_symbol: String,
     pub quote_symbol: String,
+    pub tick_size: u64,
+    pub lot_size: u64,
+    pub min_size: u64,
+    pub max_size: u64,
+    pub overflow_policy: OverflowPolicy,
+    pub min_eviction_ticks: u64,
 }
 
 #[derive(Accounts)]
@@ -36,10 +43,10 @@ pub struct NewInstrmtCtx<'info> {
     pub instrmt: Box<Account<'info, Instrmt>>,
 
     #[account(zero)]
-    pub rb_filled_exec_reports: AccountLoader<'info, RingBufferFilledExecReport>,
+    pub rb_filled_exec_reports: AccountLoader<'info, RingBufferFilledExecReportHeader>,
 
     #[account(zero)]
-    pub book: AccountLoader<'info, Book>,
+    pub book: AccountLoader<'info, BookHeader>,
 
     #[account(
         constraint = base_mint.decimals == 0
@@ -74,6 +81,11 @@ pub struct NewInstrmtCtx<'info> {
 }
 
 pub fn handler(ctx: Context<NewInstrmtCtx>, ix: NewInstrmtIx) -> Result<()> {
+    require!(
+        ix.tick_size > 0 && ix.lot_size > 0 && ix.min_size > 0 && ix.min_size <= ix.max_size,
+        ErrorCode::InvalidMarketParams
+    );
+
     let instrmt_grp = &mut ctx.accounts.instrmt_grp;
     instrmt_grp.instrmts.push(ctx.accounts.instrmt.key());
 
@@ -90,6 +102,13 @@ pub fn handler(ctx: Context<NewInstrmtCtx>, ix: NewInstrmtIx) -> Result<()> {
     instrmt.base_symbol = Instrmt::to_u8_array(ix.base_symbol);
     instrmt.quote_symbol = Instrmt::to_u8_array(ix.quote_symbol);
 
+    instrmt.tick_size = ix.tick_size;
+    instrmt.lot_size = ix.lot_size;
+    instrmt.min_size = ix.min_size;
+    instrmt.max_size = ix.max_size;
+    instrmt.overflow_policy = ix.overflow_policy;
+    instrmt.min_eviction_ticks = ix.min_eviction_ticks;
+
     instrmt.book = ctx.accounts.book.key();
     instrmt.bumps = InstrmtBumps {
         base_vault_bump: *ctx.bumps.get("base_vault").unwrap(),
@@ -97,14 +116,24 @@ pub fn handler(ctx: Context<NewInstrmtCtx>, ix: NewInstrmtIx) -> Result<()> {
         instrmt_bump: *ctx.bumps.get("instrmt").unwrap(),
     };
 
-    let rb_filled_exec_reports = &mut ctx.accounts.rb_filled_exec_reports.load_init()?;
+    let mut rb_filled_exec_reports_data = ctx
+        .accounts
+        .rb_filled_exec_reports
+        .as_ref()
+        .try_borrow_mut_data()?;
+    let rb_filled_exec_reports =
+        &mut RingBufferFilledExecReport::init(&mut rb_filled_exec_reports_data)?;
 
-    rb_filled_exec_reports.next_index = 0;
+    rb_filled_exec_reports.seq_num = 0;
     instrmt.rb_filled_exec_reports = ctx.accounts.rb_filled_exec_reports.key();
 
-    let book = &mut ctx.accounts.book.load_init()?;
+    let mut book_data = ctx.accounts.book.as_ref().try_borrow_mut_data()?;
+    let book = &mut Book::init(&mut book_data)?;
 
-    book.instrmt = instrmt.key();
+    book.set_instrmt(instrmt.key());
+    book.tick_size = ix.tick_size;
+    book.overflow_policy = ix.overflow_policy as u8;
+    book.min_eviction_ticks = ix.min_eviction_ticks;
 
     book.set_base_vault(ctx.accounts.base_vault.key());
     book.set_quote_vault(ctx.accounts.quote_vault.key());
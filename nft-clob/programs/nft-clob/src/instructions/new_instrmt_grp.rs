@@ -17,23 +17,37 @@ pub struct NewInstrmtGrpCtx<'info> {
     pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
 
     #[account(zero)]
-    pub rb_crank: AccountLoader<'info, RingBufferCrank>,
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"crank-reward-pool", instrmt_grp.key().as_ref()],
+        bump,
+        space = CrankRewardPool::space()
+    )]
+    pub crank_reward_pool: Box<Account<'info, CrankRewardPool>>,
 
     pub system_program: Program<'info, System>,
 }
 
 pub fn handler(ctx: Context<NewInstrmtGrpCtx>) -> Result<()> {
-    let mut rb_crank = ctx.accounts.rb_crank.load_init()?;
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::init(&mut rb_crank_data)?;
 
     let instrmt_grp = &mut ctx.accounts.instrmt_grp;
 
     rb_crank.instrmt_grp = instrmt_grp.key();
 
     rb_crank.head = 0;
-    rb_crank.next = 1;
+    rb_crank.next = 0;
 
     instrmt_grp.admin = ctx.accounts.authority.key();
     instrmt_grp.bump = *ctx.bumps.get("instrmt_grp").unwrap();
 
+    let crank_reward_pool = &mut ctx.accounts.crank_reward_pool;
+    crank_reward_pool.instrmt_grp = instrmt_grp.key();
+    crank_reward_pool.bump = *ctx.bumps.get("crank_reward_pool").unwrap();
+
     Ok(())
 }
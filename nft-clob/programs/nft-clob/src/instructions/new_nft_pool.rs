@@ -7,6 +7,7 @@ use crate::errors::ErrorCode;
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct NewNftPoolIx {
     pub verified_creators: Vec<Pubkey>,
+    pub collection: Option<Pubkey>,
 }
 
 #[derive(Accounts)]
@@ -21,11 +22,11 @@ pub struct NewNftPoolCtx<'info> {
         seeds = [
             b"nft-pool",
             authority.key().as_ref(),
-            ix.verified_creators.iter().fold("".to_string(), |cur, nxt| cur + &nxt.to_string()).as_ref()
+            NftPool::criteria_hash(&ix.verified_creators, &ix.collection).as_ref()
         ],
         bump,
-        space = NftPool::space(ix.verified_creators.len())
-
+        space = NftPool::space(ix.verified_creators.len()),
+        constraint = NftPool::has_criteria(&ix.verified_creators, &ix.collection) @ ErrorCode::NftPoolWithoutCriteria
     )]
     pub nft_pool: Box<Account<'info, NftPool>>,
 
@@ -39,6 +40,9 @@ pub struct NewNftPoolCtx<'info> {
     )]
     pub mint: Account<'info, Mint>,
 
+    #[account(zero)]
+    pub inventory: AccountLoader<'info, NftInventory>,
+
     pub rent: Sysvar<'info, Rent>,
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
@@ -47,9 +51,18 @@ pub struct NewNftPoolCtx<'info> {
 pub fn handler(ctx: Context<NewNftPoolCtx>, ix: NewNftPoolIx) -> Result<()> {
     let nft_pool = &mut ctx.accounts.nft_pool;
 
+    nft_pool.authority = ctx.accounts.authority.key();
     nft_pool.mint = ctx.accounts.mint.key();
     nft_pool.bump = *ctx.bumps.get("nft_pool").unwrap();
+    nft_pool.mint_bump = *ctx.bumps.get("mint").unwrap();
+    nft_pool.criteria_hash = NftPool::criteria_hash(&ix.verified_creators, &ix.collection);
     nft_pool.verified_creators = ix.verified_creators;
+    nft_pool.collection = ix.collection;
+
+    let inventory = &mut ctx.accounts.inventory.load_init()?;
+    inventory.nft_pool = nft_pool.key();
+    inventory.init();
+    nft_pool.inventory = ctx.accounts.inventory.key();
 
     Ok(())
 }
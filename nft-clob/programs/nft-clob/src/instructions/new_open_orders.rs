@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+
+#[derive(Accounts)]
+pub struct NewOpenOrdersCtx<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"instrmt", instrmt.book.as_ref()],
+        bump = instrmt.bumps.instrmt_bump,
+    )]
+    pub instrmt: Box<Account<'info, Instrmt>>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"open-orders", instrmt.key().as_ref(), authority.key().as_ref()],
+        bump,
+        space = OpenOrders::space()
+    )]
+    pub open_orders: Box<Account<'info, OpenOrders>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Opens the open orders of the authority on the instrument, the authority
+/// pays the rent.
+pub fn handler(ctx: Context<NewOpenOrdersCtx>) -> Result<()> {
+    let open_orders = &mut ctx.accounts.open_orders;
+
+    open_orders.instrmt = ctx.accounts.instrmt.key();
+    open_orders.owner = ctx.accounts.authority.key();
+    open_orders.base_vault = ctx.accounts.instrmt.base_vault;
+    open_orders.quote_vault = ctx.accounts.instrmt.quote_vault;
+    open_orders.bump = *ctx.bumps.get("open_orders").unwrap();
+    Ok(())
+}
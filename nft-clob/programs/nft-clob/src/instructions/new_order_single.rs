@@ -2,7 +2,8 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 
 use crate::account_states::*;
-use crate::enums::OrderType;
+use crate::constants::admin;
+use crate::enums::{OrderType, SelfTradeBehavior};
 use crate::errors::ErrorCode;
 
 #[derive(AnchorSerialize, AnchorDeserialize)]
@@ -11,6 +12,10 @@ pub struct NewOrderSingleIx {
     pub limit: u64,
     pub size: u64,
     pub order_type: OrderType,
+    pub client_order_id: Option<u64>,
+    pub self_trade_behavior: SelfTradeBehavior,
+    pub quote_budget: Option<u64>, // Buys only, spend at most this much quote including fees.
+    pub expiry: Option<i64>,       // Unix timestamp a GTT order expires at.
 }
 
 #[derive(Accounts)]
@@ -35,11 +40,24 @@ pub struct NewOrderSingleCtx<'info> {
     )]
     pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
 
+    #[account(
+        seeds = [b"master-cfg", admin::ID.as_ref()],
+        bump = master_cfg.bump,
+    )]
+    pub master_cfg: Box<Account<'info, MasterCfg>>,
+
+    #[account(
+        mut,
+        seeds = [b"crank-reward-pool", instrmt_grp.key().as_ref()],
+        bump = crank_reward_pool.bump,
+    )]
+    pub crank_reward_pool: Box<Account<'info, CrankRewardPool>>,
+
     #[account(mut)]
-    pub rb_filled_exec_reports: AccountLoader<'info, RingBufferFilledExecReport>,
+    pub rb_filled_exec_reports: AccountLoader<'info, RingBufferFilledExecReportHeader>,
 
     #[account(mut, constraint = rb_crank.load()?.instrmt_grp == instrmt.instrmt_grp)]
-    pub rb_crank: AccountLoader<'info, RingBufferCrank>,
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
 
     #[account(mut)]
     pub base_vault: Box<Account<'info, TokenAccount>>,
@@ -54,7 +72,15 @@ pub struct NewOrderSingleCtx<'info> {
     pub quote_user_token_account: Box<Account<'info, TokenAccount>>,
 
     #[account(mut)]
-    pub book: AccountLoader<'info, Book>,
+    pub book: AccountLoader<'info, BookHeader>,
+
+    /// Settles through the open orders of the authority if set.
+    #[account(
+        mut,
+        seeds = [b"open-orders", instrmt.key().as_ref(), authority.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Option<Box<Account<'info, OpenOrders>>>,
 
     pub system_program: Program<'info, System>,
 
@@ -86,103 +112,161 @@ impl<'info> NewOrderSingleCtx<'info> {
 }
 
 pub fn handler(ctx: Context<NewOrderSingleCtx>, ix: NewOrderSingleIx) -> Result<()> {
-    let book = &mut ctx.accounts.book.load_mut()?;
-    let rb_filled_exec_reports = &mut ctx.accounts.rb_filled_exec_reports.load_mut()?;
-    let rb_crank = &mut ctx.accounts.rb_crank.load_mut()?;
+    let mut book_data = ctx.accounts.book.as_ref().try_borrow_mut_data()?;
+    let book = &mut Book::load_mut(&mut book_data)?;
+    let mut rb_filled_exec_reports_data = ctx
+        .accounts
+        .rb_filled_exec_reports
+        .as_ref()
+        .try_borrow_mut_data()?;
+    let rb_filled_exec_reports =
+        &mut RingBufferFilledExecReport::load_mut(&mut rb_filled_exec_reports_data)?;
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+    let clock = Clock::get()?;
 
-    let (payment_acc, payout_acc) = match ix.is_buy {
-        true => (
+    // Open orders receive the payouts and refunds the crank queues for the
+    // order in place of the token accounts.
+    let (payment_acc, payout_acc) = match (&ctx.accounts.open_orders, ix.is_buy) {
+        (Some(open_orders), _) => (open_orders.key(), open_orders.key()),
+        (None, true) => (
             ctx.accounts.quote_user_token_account.key(),
             ctx.accounts.base_user_token_account.key(),
         ),
-        false => (
+        (None, false) => (
             ctx.accounts.base_user_token_account.key(),
             ctx.accounts.quote_user_token_account.key(),
         ),
     };
 
-    let order = book.new_limit_from_nos_ix(
-        &ix,
-        ctx.accounts.authority.key(),
-        payout_acc,
-        payment_acc,
+    // Market orders have no limit price.
+    if ix.order_type != OrderType::MKT {
+        ctx.accounts.instrmt.validate_limit(ix.limit)?;
+    }
+    ctx.accounts.instrmt.validate_size(ix.size)?;
+
+    if let Some(quote_budget) = ix.quote_budget {
+        require!(
+            ix.is_buy
+                && quote_budget > 0
+                && (ix.order_type == OrderType::IOC || ix.order_type == OrderType::MKT),
+            ErrorCode::InvalidQuoteBudget
+        );
+    }
+
+    match ix.order_type {
+        OrderType::GTT => require!(
+            ix.expiry.unwrap_or(0) > clock.unix_timestamp,
+            ErrorCode::InvalidExpiry
+        ),
+        _ => require!(ix.expiry.is_none(), ErrorCode::InvalidExpiry),
+    }
+
+    let mut new_order = ix.into_order(ctx.accounts.authority.key(), payout_acc, payment_acc);
+    let (maker_fee_bps, taker_fee_bps) = ctx
+        .accounts
+        .master_cfg
+        .get_fees_bps(&ctx.accounts.authority.key());
+    new_order.set_fees(maker_fee_bps, taker_fee_bps);
+    new_order.set_uses_open_orders(ctx.accounts.open_orders.is_some());
+
+    let mut open_orders = ctx
+        .accounts
+        .open_orders
+        .as_deref_mut()
+        .map(|open_orders| &mut **open_orders);
+
+    let cranks_queued = rb_crank.len();
+    let order = book.new_limit(
+        new_order,
+        ix.order_type,
+        ix.is_buy,
         rb_filled_exec_reports,
         rb_crank,
-    );
+        open_orders.as_deref_mut(),
+        &clock,
+    )?;
 
     match ix.order_type {
         // All good. No checks required.
-        OrderType::GTC => {}
+        OrderType::GTC | OrderType::GTT => {}
         OrderType::FOK => require!(order.is_filled(), ErrorCode::FillOrKillFailed),
         OrderType::IOC => require!(
             order.get_leaves_qty() == 0,
             ErrorCode::ImmediateOrCancelError
         ),
-        OrderType::MO => require!(!order.is_partially_filed(), ErrorCode::MakerOnlyFailed),
+        // Checked by the book before matching.
+        OrderType::MO | OrderType::MOS => {}
+        OrderType::MKT => require!(
+            order.get_leaves_qty() == 0,
+            ErrorCode::ImmediateOrCancelError
+        ),
     };
 
+    // Fund the keepers processing the cranks this order queued, and the one
+    // refunding it if it rests.
+    let cranks = (rb_crank.len() - cranks_queued) as u64 + !order.is_filled() as u64;
+    CrankRewardPool::charge_reward(
+        &ctx.accounts.crank_reward_pool.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        CrankRewardPool::reward(ctx.accounts.instrmt_grp.crank_reward_lamports, cranks)?,
+    )?;
+
     let instrmt_grp_seeds = &[
         b"instrmt-grp".as_ref(),
-        &ctx.accounts.instrmt_grp.admin.as_ref(),
+        ctx.accounts.instrmt_grp.admin.as_ref(),
         &[ctx.accounts.instrmt_grp.bump],
     ];
 
     let signer = &[&instrmt_grp_seeds[..]];
 
-    if ix.is_buy {
-        // user deposit quote.
-        // user receive base if partially filled.
-        let leaves_cost: u64 = order.get_leaves_cost().unwrap();
-        let user_deposit_qty = leaves_cost.checked_add(order.get_cum_cost()).unwrap();
-        token::transfer(
-            ctx.accounts.into_quote_transfer_user_to_vault(),
-            user_deposit_qty,
-        )?;
+    // Buyers deposit quote including fees and receive base if partially filled.
+    // Sellers deposit base and receive quote minus fees if partially filled.
+    // For IOC get_leaves_qty() has been set to 0!
+    let (deposit, proceeds) = order.get_settlement(ix.is_buy).unwrap();
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.base_vault.to_account_info().clone(),
-            to: ctx
-                .accounts
-                .base_user_token_account
-                .to_account_info()
-                .clone(),
-            authority: ctx.accounts.instrmt_grp.to_account_info().clone(),
-        };
-        let cpi_context = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        token::transfer(cpi_context, order.get_cum_qty())?;
-    } else {
-        // user deposit base.
-        // user receive quote but only the curent cumulative quantity.
-        // For IOC get_leaves_qty() has been set to 0!
-        let user_deposit_qty = order
-            .get_cum_qty()
-            .checked_add(order.get_leaves_qty())
-            .unwrap();
-        token::transfer(
+    // Open orders fund the deposit from their free balance first and keep
+    // the proceeds for the owner to settle.
+    let (user_deposit_qty, user_proceeds_qty) = match open_orders {
+        Some(open_orders) => {
+            let free_deposit = open_orders.take_free(ix.is_buy, deposit);
+            open_orders.lock(ix.is_buy, order.get_leaves_deposit(ix.is_buy).unwrap());
+            open_orders.credit_proceeds(ix.is_buy, proceeds);
+            (deposit - free_deposit, 0)
+        }
+        None => (deposit, proceeds),
+    };
+
+    let (deposit_transfer, payout_from, payout_to) = match ix.is_buy {
+        true => (
+            ctx.accounts.into_quote_transfer_user_to_vault(),
+            &ctx.accounts.base_vault,
+            &ctx.accounts.base_user_token_account,
+        ),
+        false => (
             ctx.accounts.into_base_transfer_user_to_vault(),
-            user_deposit_qty,
-        )?;
+            &ctx.accounts.quote_vault,
+            &ctx.accounts.quote_user_token_account,
+        ),
+    };
 
+    if user_deposit_qty > 0 {
+        token::transfer(deposit_transfer, user_deposit_qty)?;
+    }
+
+    if user_proceeds_qty > 0 {
         let cpi_accounts = Transfer {
-            from: ctx.accounts.quote_vault.to_account_info().clone(),
-            to: ctx
-                .accounts
-                .quote_user_token_account
-                .to_account_info()
-                .clone(),
-            authority: ctx.accounts.instrmt_grp.to_account_info().clone(),
+            from: payout_from.to_account_info(),
+            to: payout_to.to_account_info(),
+            authority: ctx.accounts.instrmt_grp.to_account_info(),
         };
         let cpi_context = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             cpi_accounts,
             signer,
         );
-
-        token::transfer(cpi_context, order.get_cum_cost())?;
+        token::transfer(cpi_context, user_proceeds_qty)?;
     }
     Ok(())
 }
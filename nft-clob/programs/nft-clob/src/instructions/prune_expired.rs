@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct PruneExpiredIx {
+    pub max_orders: u16, // Orders visited across both sides.
+}
+
+#[derive(Accounts)]
+pub struct PruneExpiredCtx<'info> {
+    #[account(
+        seeds = [b"instrmt", book.key().as_ref()],
+        bump = instrmt.bumps.instrmt_bump,
+        constraint = instrmt.book == book.key(),
+    )]
+    pub instrmt: Box<Account<'info, Instrmt>>,
+
+    #[account(mut)]
+    pub book: AccountLoader<'info, BookHeader>,
+
+    #[account(mut, constraint = rb_crank.load()?.instrmt_grp == instrmt.instrmt_grp)]
+    pub rb_crank: AccountLoader<'info, RingBufferCrankHeader>,
+}
+
+/// Permissionless sweep of expired orders, refunded to their makers.
+/// Returns the number of orders pruned.
+pub fn handler(ctx: Context<PruneExpiredCtx>, ix: PruneExpiredIx) -> Result<u16> {
+    let mut book_data = ctx.accounts.book.as_ref().try_borrow_mut_data()?;
+    let book = &mut Book::load_mut(&mut book_data)?;
+    let mut rb_crank_data = ctx.accounts.rb_crank.as_ref().try_borrow_mut_data()?;
+    let rb_crank = &mut RingBufferCrank::load_mut(&mut rb_crank_data)?;
+    let clock = Clock::get()?;
+
+    book.prune_expired(ix.max_orders, rb_crank, &clock)
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+use crate::constants::ADMIN_PUBKEY_STR;
+use crate::errors::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RemoveFeeExemptIx {
+    pub client: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFeeExemptCtx<'info> {
+    #[account(
+        mut,
+        constraint = admin.key.to_string() == ADMIN_PUBKEY_STR @ ErrorCode::NotAdmin
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"master-cfg", admin.key().as_ref()],
+        bump = master_cfg.bump,
+        realloc = MasterCfg::space(master_cfg.fee_exempt.len().saturating_sub(1)),
+        realloc::payer = admin,
+        realloc::zero = false,
+    )]
+    pub master_cfg: Box<Account<'info, MasterCfg>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<RemoveFeeExemptCtx>, ix: RemoveFeeExemptIx) -> Result<()> {
+    let master_cfg = &mut ctx.accounts.master_cfg;
+
+    let pos = master_cfg
+        .fee_exempt
+        .iter()
+        .position(|client| *client == ix.client)
+        .ok_or(ErrorCode::NotFeeExempt)?;
+    master_cfg.fee_exempt.swap_remove(pos);
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::account_states::*;
+
+#[derive(Accounts)]
+pub struct SettleFundsCtx<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"instrmt", instrmt.book.as_ref()],
+        bump = instrmt.bumps.instrmt_bump,
+        constraint = instrmt.base_vault == base_vault.key(),
+        constraint = instrmt.quote_vault == quote_vault.key(),
+        constraint = instrmt.base_mint == base_user_token_account.mint,
+        constraint = instrmt.quote_mint == quote_user_token_account.mint,
+    )]
+    pub instrmt: Box<Account<'info, Instrmt>>,
+
+    #[account(
+        constraint = instrmt_grp.key() == instrmt.instrmt_grp
+    )]
+    pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
+
+    #[account(
+        mut,
+        seeds = [b"open-orders", instrmt.key().as_ref(), authority.key().as_ref()],
+        bump = open_orders.bump,
+    )]
+    pub open_orders: Box<Account<'info, OpenOrders>>,
+
+    #[account(mut)]
+    pub base_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub quote_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, constraint = base_user_token_account.owner == authority.key())]
+    pub base_user_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, constraint = quote_user_token_account.owner == authority.key())]
+    pub quote_user_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Withdraws the free base and quote balances of the owner.
+pub fn handler(ctx: Context<SettleFundsCtx>) -> Result<()> {
+    let open_orders: &mut OpenOrders = &mut ctx.accounts.open_orders;
+    let base_free = open_orders.take_free(false, open_orders.base_free);
+    let quote_free = open_orders.take_free(true, open_orders.quote_free);
+
+    let instrmt_grp_seeds = &[
+        b"instrmt-grp".as_ref(),
+        ctx.accounts.instrmt_grp.admin.as_ref(),
+        &[ctx.accounts.instrmt_grp.bump],
+    ];
+
+    let signer = &[&instrmt_grp_seeds[..]];
+
+    for (vault, user, amount) in [
+        (
+            &ctx.accounts.base_vault,
+            &ctx.accounts.base_user_token_account,
+            base_free,
+        ),
+        (
+            &ctx.accounts.quote_vault,
+            &ctx.accounts.quote_user_token_account,
+            quote_free,
+        ),
+    ] {
+        if amount == 0 {
+            continue;
+        }
+        let cpi_accounts = Transfer {
+            from: vault.to_account_info(),
+            to: user.to_account_info(),
+            authority: ctx.accounts.instrmt_grp.to_account_info(),
+        };
+        let cpi_context = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_context, amount)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, CloseAccount, Mint, Token, TokenAccount, Transfer};
+
+use crate::account_states::*;
+use crate::errors::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SwapFtIx {
+    pub inventory_pos: Option<u16>, // Position of the NFT to redeem. The oldest deposit if not set.
+}
+
+#[derive(Accounts)]
+pub struct SwapFtCtx<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [
+            b"nft-pool",
+            nft_pool.authority.as_ref(),
+            nft_pool.criteria_hash.as_ref()
+        ],
+        bump = nft_pool.bump,
+    )]
+    pub nft_pool: Box<Account<'info, NftPool>>,
+
+    #[account(mut, constraint = inventory.load()?.nft_pool == nft_pool.key())]
+    pub inventory: AccountLoader<'info, NftInventory>,
+
+    #[account(
+        mut,
+        address = nft_pool.mint
+    )]
+    pub pool_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = pool_mint.key() == pool_token_account.mint,
+        constraint = authority.key() == pool_token_account.owner
+    )]
+    pub pool_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub nft_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        seeds = [b"nft-vault", nft_pool.key().as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = nft_mint.key() == nft_token_account.mint,
+        constraint = authority.key() == nft_token_account.owner
+    )]
+    pub nft_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Checked against the depositor recorded in the inventory.
+    #[account(mut)]
+    pub depositor: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> SwapFtCtx<'info> {
+    pub fn into_pool_token_burn(&self) -> CpiContext<'_, '_, '_, 'info, Burn<'info>> {
+        let cpi_accounts = Burn {
+            mint: self.pool_mint.to_account_info().clone(),
+            from: self.pool_token_account.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+pub fn handler(ctx: Context<SwapFtCtx>, ix: SwapFtIx) -> Result<()> {
+    let inventory = &mut ctx.accounts.inventory.load_mut()?;
+
+    let pos = match ix.inventory_pos {
+        None => inventory.oldest().ok_or(ErrorCode::NftPoolEmpty)?,
+        Some(pos) => pos,
+    };
+    let entry = inventory.get(pos).ok_or(ErrorCode::NftNotInPool)?;
+    require!(
+        entry.mint == ctx.accounts.nft_mint.key(),
+        ErrorCode::NftNotInPool
+    );
+    require!(
+        entry.depositor == ctx.accounts.depositor.key(),
+        ErrorCode::WrongDepositor
+    );
+    inventory.remove(pos);
+
+    // One pool token is worth exactly one NFT.
+    token::burn(ctx.accounts.into_pool_token_burn(), 1)?;
+
+    let nft_pool = &ctx.accounts.nft_pool;
+    let nft_pool_seeds = &[
+        b"nft-pool".as_ref(),
+        nft_pool.authority.as_ref(),
+        nft_pool.criteria_hash.as_ref(),
+        &[nft_pool.bump],
+    ];
+    let signer = &[&nft_pool_seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.nft_vault.to_account_info().clone(),
+        to: ctx.accounts.nft_token_account.to_account_info().clone(),
+        authority: nft_pool.to_account_info().clone(),
+    };
+    let cpi_context = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer,
+    );
+    token::transfer(cpi_context, 1)?;
+
+    // The vault is empty, return its rent to the depositor who paid it.
+    let cpi_accounts = CloseAccount {
+        account: ctx.accounts.nft_vault.to_account_info().clone(),
+        destination: ctx.accounts.depositor.to_account_info().clone(),
+        authority: nft_pool.to_account_info().clone(),
+    };
+    let cpi_context = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer,
+    );
+    token::close_account(cpi_context)
+}
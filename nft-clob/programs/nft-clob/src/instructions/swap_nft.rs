@@ -1,44 +1,119 @@
-use crate::account_states::*;
-use crate::errors::ErrorCode;
 use anchor_lang::prelude::*;
-use anchor_spl::metadata::{MasterEditionAccount, Metadata};
-use anchor_spl::token::{Mint, TokenAccount};
+use anchor_spl::metadata::{MasterEditionAccount, Metadata, MetadataAccount};
+use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount, Transfer};
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
-pub struct SwapNftIx {}
+use crate::account_states::*;
+use crate::errors::ErrorCode;
 
 #[derive(Accounts)]
 pub struct SwapNftCtx<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    pub nft_pool: Box<Account<'info, NftPool>>,
+
+    #[account(mut, constraint = inventory.load()?.nft_pool == nft_pool.key())]
+    pub inventory: AccountLoader<'info, NftInventory>,
+
+    #[account(
+        mut,
+        address = nft_pool.mint
+    )]
+    pub pool_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = pool_mint.key() == pool_token_account.mint,
+        constraint = authority.key() == pool_token_account.owner
+    )]
+    pub pool_token_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(constraint = nft_mint.decimals == 0 && nft_mint.supply == 1 @ ErrorCode::NotAnNft)]
     pub nft_mint: Box<Account<'info, Mint>>,
 
     #[account(
+        mut,
         constraint = nft_mint.key() == nft_token_account.mint,
         constraint = authority.key() == nft_token_account.owner,
         constraint = 1 == nft_token_account.amount
     )]
     pub nft_token_account: Box<Account<'info, TokenAccount>>,
 
-    /// CHECK:
     #[account(
-        // address = Metadata::id(),
+        init,
+        payer = authority,
+        seeds = [b"nft-vault", nft_pool.key().as_ref(), nft_mint.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = nft_pool,
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
         seeds = [b"metadata", Metadata::id().as_ref(), nft_mint.key().as_ref()],
         seeds::program = Metadata::id(),
-        bump
+        bump,
+        constraint = nft_pool.is_member(&nft_metadata_account) @ ErrorCode::UnverifiedNft
     )]
-    pub nft_metadata_account: AccountInfo<'info>,
+    pub nft_metadata_account: Box<Account<'info, MetadataAccount>>,
 
     #[account(
         seeds = [b"metadata", Metadata::id().as_ref(), nft_mint.key().as_ref(), b"edition"],
         seeds::program = Metadata::id(),
-        bump,
-        constraint = master_edition.supply > 0
+        bump
     )]
     pub master_edition: Box<Account<'info, MasterEditionAccount>>,
+
+    pub rent: Sysvar<'info, Rent>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-pub fn handler(ctx: Context<SwapNftCtx>, ix: SwapNftIx) -> Result<()> {
-    Ok(())
+impl<'info> SwapNftCtx<'info> {
+    pub fn into_nft_transfer_user_to_vault(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: self.nft_token_account.to_account_info().clone(),
+            to: self.nft_vault.to_account_info().clone(),
+            authority: self.authority.to_account_info().clone(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+pub fn handler(ctx: Context<SwapNftCtx>) -> Result<()> {
+    // Escrow the NFT in the pool.
+    token::transfer(ctx.accounts.into_nft_transfer_user_to_vault(), 1)?;
+
+    let inventory = &mut ctx.accounts.inventory.load_mut()?;
+    require!(
+        inventory
+            .insert(
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.authority.key(),
+                Clock::get()?.slot,
+            )
+            .is_some(),
+        ErrorCode::NftInventoryFull
+    );
+
+    // The pool mint is its own authority, derived from the pool.
+    let nft_pool_key = ctx.accounts.nft_pool.key();
+    let mint_seeds = &[nft_pool_key.as_ref(), &[ctx.accounts.nft_pool.mint_bump]];
+    let signer = &[&mint_seeds[..]];
+
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.pool_mint.to_account_info().clone(),
+        to: ctx.accounts.pool_token_account.to_account_info().clone(),
+        authority: ctx.accounts.pool_mint.to_account_info().clone(),
+    };
+    let cpi_context = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer,
+    );
+
+    // One NFT is worth exactly one pool token.
+    token::mint_to(cpi_context, 1)
 }
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+use crate::account_states::*;
+use crate::constants::ADMIN_PUBKEY_STR;
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct SweepFeesCtx<'info> {
+    #[account(
+        constraint = admin.key.to_string() == ADMIN_PUBKEY_STR @ ErrorCode::NotAdmin
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"master-cfg", admin.key().as_ref()],
+        bump = master_cfg.bump,
+    )]
+    pub master_cfg: Box<Account<'info, MasterCfg>>,
+
+    #[account(
+        seeds = [b"instrmt", book.key().as_ref()],
+        bump = instrmt.bumps.instrmt_bump,
+        constraint = instrmt.book == book.key(),
+    )]
+    pub instrmt: Box<Account<'info, Instrmt>>,
+
+    #[account(
+        constraint = instrmt_grp.key() == instrmt.instrmt_grp
+    )]
+    pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
+
+    #[account(mut)]
+    pub book: AccountLoader<'info, BookHeader>,
+
+    #[account(
+        mut,
+        constraint = quote_vault.key() == instrmt.quote_vault @ ErrorCode::WrongVaultAccount
+    )]
+    pub quote_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = fee_treasury.owner == master_cfg.fee_treasury @ ErrorCode::WrongTokenAccount,
+        constraint = fee_treasury.mint == instrmt.quote_mint @ ErrorCode::WrongTokenAccount
+    )]
+    pub fee_treasury: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Fees are charged in quote only, hence the base vault never holds fees.
+pub fn handler(ctx: Context<SweepFeesCtx>) -> Result<()> {
+    let book = &mut ctx.accounts.book.load_mut()?;
+    let fees_accrued = book.fees_accrued;
+
+    if fees_accrued == 0 {
+        return Ok(());
+    }
+    book.fees_accrued = 0;
+
+    let instrmt_grp_seeds = &[
+        b"instrmt-grp".as_ref(),
+        ctx.accounts.instrmt_grp.admin.as_ref(),
+        &[ctx.accounts.instrmt_grp.bump],
+    ];
+
+    let signer = &[&instrmt_grp_seeds[..]];
+
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.quote_vault.to_account_info(),
+        to: ctx.accounts.fee_treasury.to_account_info(),
+        authority: ctx.accounts.instrmt_grp.to_account_info(),
+    };
+    let cpi_context = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer,
+    );
+
+    token::transfer(cpi_context, fees_accrued)?;
+
+    Ok(())
+}
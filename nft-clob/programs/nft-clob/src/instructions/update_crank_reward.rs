@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+use crate::errors::ErrorCode;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpdateCrankRewardIx {
+    pub crank_reward_lamports: u64,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCrankRewardCtx<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"instrmt-grp", authority.key().as_ref()],
+        bump = instrmt_grp.bump,
+        constraint = instrmt_grp.admin == authority.key() @ ErrorCode::NotAdmin
+    )]
+    pub instrmt_grp: Box<Account<'info, InstrmtGrp>>,
+}
+
+pub fn handler(ctx: Context<UpdateCrankRewardCtx>, ix: UpdateCrankRewardIx) -> Result<()> {
+    let instrmt_grp = &mut ctx.accounts.instrmt_grp;
+
+    instrmt_grp.crank_reward_lamports = ix.crank_reward_lamports;
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anchor_lang::prelude::*;
+
+use crate::account_states::*;
+use crate::constants::{ADMIN_PUBKEY_STR, MAX_MAKER_FEE_BPS, MAX_TAKER_FEE_BPS};
+use crate::errors::ErrorCode;
+
+/// Fields left as `None` keep their current value.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpdateMasterCfgIx {
+    pub maker_fee_bps: Option<u8>,
+    pub taker_fee_bps: Option<u8>,
+    pub fee_treasury: Option<Pubkey>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMasterCfgCtx<'info> {
+    #[account(
+        constraint = admin.key.to_string() == ADMIN_PUBKEY_STR @ ErrorCode::NotAdmin
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"master-cfg", admin.key().as_ref()],
+        bump = master_cfg.bump,
+    )]
+    pub master_cfg: Box<Account<'info, MasterCfg>>,
+}
+
+pub fn handler(ctx: Context<UpdateMasterCfgCtx>, ix: UpdateMasterCfgIx) -> Result<()> {
+    let master_cfg = &mut ctx.accounts.master_cfg;
+
+    if let Some(maker_fee_bps) = ix.maker_fee_bps {
+        require!(maker_fee_bps <= MAX_MAKER_FEE_BPS, ErrorCode::FeeTooHigh);
+        master_cfg.maker_fee_bps = maker_fee_bps;
+    }
+    if let Some(taker_fee_bps) = ix.taker_fee_bps {
+        require!(taker_fee_bps <= MAX_TAKER_FEE_BPS, ErrorCode::FeeTooHigh);
+        master_cfg.taker_fee_bps = taker_fee_bps;
+    }
+    if let Some(fee_treasury) = ix.fee_treasury {
+        master_cfg.fee_treasury = fee_treasury;
+    }
+
+    Ok(())
+}
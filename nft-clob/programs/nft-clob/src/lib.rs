@@ -1,7 +1,10 @@
+#![allow(clippy::result_large_err)]
+
 pub mod account_states;
 pub mod constants;
 pub mod enums;
 pub mod errors;
+pub mod events;
 pub mod instructions;
 
 use anchor_lang::prelude::*;
@@ -13,14 +16,48 @@ declare_id!("5pEBe82QbyavqRpBZHwGJLi8RofJaLWbP2FSDRLoUWZM");
 pub mod nft_clob {
     use super::*;
 
+    pub fn add_fee_exempt(ctx: Context<AddFeeExemptCtx>, ix: AddFeeExemptIx) -> Result<()> {
+        add_fee_exempt::handler(ctx, ix)
+    }
+
+    pub fn amend_order(ctx: Context<AmendOrderCtx>, ix: AmendOrderIx) -> Result<()> {
+        amend_order::handler(ctx, ix)
+    }
+
+    pub fn cancel_all_orders(
+        ctx: Context<CancelOrderCtx>,
+        ix: CancelAllOrdersIx,
+    ) -> Result<Option<CancelAllCursor>> {
+        cancel_all_orders::handler(ctx, ix)
+    }
+
     pub fn cancel_order(ctx: Context<CancelOrderCtx>, ix: CancelOrderIx) -> Result<()> {
         cancel_order::handler(ctx, ix)
     }
 
+    pub fn cancel_order_by_client_id(
+        ctx: Context<CancelOrderCtx>,
+        ix: CancelOrderByClientIdIx,
+    ) -> Result<()> {
+        cancel_order_by_client_id::handler(ctx, ix)
+    }
+
+    pub fn close_open_orders(ctx: Context<CloseOpenOrdersCtx>) -> Result<()> {
+        close_open_orders::handler(ctx)
+    }
+
     pub fn crank(ctx: Context<CrankCtx>) -> Result<()> {
         crank::handler(ctx)
     }
 
+    pub fn crank_many<'info>(ctx: Context<'_, '_, '_, 'info, CrankManyCtx<'info>>) -> Result<()> {
+        crank_many::handler(ctx)
+    }
+
+    pub fn crank_queue_depth(ctx: Context<CrankQueueDepthCtx>) -> Result<u16> {
+        crank_queue_depth::handler(ctx)
+    }
+
     pub fn init_master_cfg(ctx: Context<InitMasterCfgCtx>, ix: InitMasterCfgIx) -> Result<()> {
         init_master_cfg::handler(ctx, ix)
     }
@@ -37,7 +74,52 @@ pub mod nft_clob {
         new_nft_pool::handler(ctx, ix)
     }
 
+    pub fn new_open_orders(ctx: Context<NewOpenOrdersCtx>) -> Result<()> {
+        new_open_orders::handler(ctx)
+    }
+
     pub fn new_order_single(ctx: Context<NewOrderSingleCtx>, ix: NewOrderSingleIx) -> Result<()> {
         new_order_single::handler(ctx, ix)
     }
+
+    pub fn prune_expired(ctx: Context<PruneExpiredCtx>, ix: PruneExpiredIx) -> Result<u16> {
+        prune_expired::handler(ctx, ix)
+    }
+
+    pub fn remove_fee_exempt(
+        ctx: Context<RemoveFeeExemptCtx>,
+        ix: RemoveFeeExemptIx,
+    ) -> Result<()> {
+        remove_fee_exempt::handler(ctx, ix)
+    }
+
+    pub fn settle_funds(ctx: Context<SettleFundsCtx>) -> Result<()> {
+        settle_funds::handler(ctx)
+    }
+
+    pub fn swap_ft(ctx: Context<SwapFtCtx>, ix: SwapFtIx) -> Result<()> {
+        swap_ft::handler(ctx, ix)
+    }
+
+    pub fn swap_nft(ctx: Context<SwapNftCtx>) -> Result<()> {
+        swap_nft::handler(ctx)
+    }
+
+    pub fn sweep_fees(ctx: Context<SweepFeesCtx>) -> Result<()> {
+        sweep_fees::handler(ctx)
+    }
+
+    pub fn update_crank_reward(
+        ctx: Context<UpdateCrankRewardCtx>,
+        ix: UpdateCrankRewardIx,
+    ) -> Result<()> {
+        update_crank_reward::handler(ctx, ix)
+    }
+
+    pub fn update_master_cfg(
+        ctx: Context<UpdateMasterCfgCtx>,
+        ix: UpdateMasterCfgIx,
+    ) -> Result<()> {
+        update_master_cfg::handler(ctx, ix)
+    }
 }
@@ -28,11 +28,17 @@ describe("nft-clob", () => {
   let quoteAta: anchor.web3.PublicKey;
   let baseVault: anchor.web3.PublicKey;
   let quoteVault: anchor.web3.PublicKey;
+  let crankRewardPool: anchor.web3.PublicKey;
 
   const rbCrank = anchor.web3.Keypair.generate();
   const rbFilledExecReports = anchor.web3.Keypair.generate();
   const book = anchor.web3.Keypair.generate();
 
+  // Account sizes, capacities are derived from them on init.
+  const bookSpace = 885_000; // 2048 orders per side.
+  const rbCrankSpace = 458_800; // 4095 cranks.
+  const rbFilledExecReportsSpace = 32_024; // 200 reports.
+
   console.log(program.programId.toBase58());
   before(async () => {
     const airdrop1 = await program.provider.connection.requestAirdrop(
@@ -46,6 +52,11 @@ describe("nft-clob", () => {
       program.programId
     );
 
+    [crankRewardPool] = await anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("crank-reward-pool"), instrmtGrp.toBuffer()],
+      program.programId
+    );
+
     [instrmt] = await anchor.web3.PublicKey.findProgramAddressSync(
       [Buffer.from("instrmt"), book.publicKey.toBuffer()],
       program.programId
@@ -119,9 +130,13 @@ describe("nft-clob", () => {
         authority: authority.publicKey,
         instrmtGrp: instrmtGrp,
         rbCrank: rbCrank.publicKey,
+        crankRewardPool: crankRewardPool,
       })
       .preInstructions([
-        await program.account.ringBufferCrank.createInstruction(rbCrank),
+        await program.account.ringBufferCrankHeader.createInstruction(
+          rbCrank,
+          rbCrankSpace
+        ),
       ])
       .signers([rbCrank, authority])
       .rpc();
@@ -134,6 +149,12 @@ describe("nft-clob", () => {
       .newInstrmt({
         baseSymbol: "BASEBASE",
         quoteSymbol: "QUOTEQUOTE",
+        tickSize: new BN(1),
+        lotSize: new BN(1),
+        minSize: new BN(1),
+        maxSize: new BN(1_000_000),
+        overflowPolicy: { reject: {} } as never,
+        minEvictionTicks: new BN(0),
       })
       .accounts({
         authority: authority.publicKey,
@@ -147,9 +168,10 @@ describe("nft-clob", () => {
         quoteVault: quoteVault,
       })
       .preInstructions([
-        await program.account.book.createInstruction(book),
-        await program.account.ringBufferFilledExecReport.createInstruction(
-          rbFilledExecReports
+        await program.account.bookHeader.createInstruction(book, bookSpace),
+        await program.account.ringBufferFilledExecReportHeader.createInstruction(
+          rbFilledExecReports,
+          rbFilledExecReportsSpace
         ),
       ])
       .signers([book, rbFilledExecReports, authority])
@@ -166,11 +188,16 @@ describe("nft-clob", () => {
         limit: new BN(2),
         size: new BN(4),
         orderType: orderType,
+        clientOrderId: null,
+        selfTradeBehavior: { decrementAndCancel: {} } as never,
+        quoteBudget: null,
+        expiry: null,
       })
       .accounts({
         authority: authority.publicKey,
         instrmt: instrmt,
         instrmtGrp: instrmtGrp,
+        crankRewardPool: crankRewardPool,
         rbFilledExecReports: rbFilledExecReports.publicKey,
         rbCrank: rbCrank.publicKey,
         baseVault: baseVault,
@@ -178,6 +205,7 @@ describe("nft-clob", () => {
         baseUserTokenAccount: baseAta,
         quoteUserTokenAccount: quoteAta,
         book: book.publicKey,
+        openOrders: null,
       })
       .signers([authority])
       .rpc();